@@ -0,0 +1,176 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// What a tracked background analysis is doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnalysisKind {
+    /// Ollama task breakdown before spawning slices
+    Coordination,
+    /// DeepSeek nudge for a stalled conversation
+    StallIntervention,
+    /// Ollama verdict on enabling a denied tool
+    ToolSafety,
+    /// Automode deciding how to answer Claude's last message
+    Automode,
+    /// Summary of a closed slice's work for the main slice
+    CloseSummary,
+}
+
+impl AnalysisKind {
+    pub const ALL: [AnalysisKind; 5] = [
+        AnalysisKind::Coordination,
+        AnalysisKind::StallIntervention,
+        AnalysisKind::ToolSafety,
+        AnalysisKind::Automode,
+        AnalysisKind::CloseSummary,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AnalysisKind::Coordination => "coordination",
+            AnalysisKind::StallIntervention => "stall",
+            AnalysisKind::ToolSafety => "tool-safety",
+            AnalysisKind::Automode => "automode",
+            AnalysisKind::CloseSummary => "summary",
+        }
+    }
+}
+
+impl fmt::Display for AnalysisKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+struct TrackedTask {
+    id: u64,
+    kind: AnalysisKind,
+    detail: String,
+    started: Instant,
+    handle: JoinHandle<()>,
+}
+
+/// A running analysis as shown by `!cancel`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskSummary {
+    pub id: u64,
+    pub kind: AnalysisKind,
+    pub detail: String,
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for TaskSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{} {} ({}, {}s)", self.id, self.kind, self.detail, self.elapsed.as_secs())
+    }
+}
+
+/// Spawned analysis tasks that are still running, so they can be listed and aborted
+#[derive(Default)]
+pub struct AnalysisTasks {
+    next_id: u64,
+    tasks: Vec<TrackedTask>,
+}
+
+impl AnalysisTasks {
+    /// Start tracking a spawned task; returns its id for `!cancel`
+    pub fn track(&mut self, kind: AnalysisKind, detail: impl Into<String>, handle: JoinHandle<()>) -> u64 {
+        self.prune();
+        self.next_id += 1;
+        self.tasks.push(TrackedTask {
+            id: self.next_id,
+            kind,
+            detail: detail.into(),
+            started: Instant::now(),
+            handle,
+        });
+        self.next_id
+    }
+
+    /// Forget tasks that have already finished
+    pub fn prune(&mut self) {
+        self.tasks.retain(|task| !task.handle.is_finished());
+    }
+
+    /// Running tasks, oldest first
+    pub fn running(&self) -> Vec<TaskSummary> {
+        self.tasks
+            .iter()
+            .filter(|task| !task.handle.is_finished())
+            .map(|task| TaskSummary {
+                id: task.id,
+                kind: task.kind,
+                detail: task.detail.clone(),
+                elapsed: task.started.elapsed(),
+            })
+            .collect()
+    }
+
+    pub fn running_count(&self) -> usize {
+        self.tasks.iter().filter(|task| !task.handle.is_finished()).count()
+    }
+
+    /// Abort tasks matching `target`: an id (`3` or `#3`), a kind name, or `all`.
+    /// Returns what was cancelled.
+    pub fn cancel(&mut self, target: &str) -> Vec<TaskSummary> {
+        self.prune();
+        let target = target.trim().trim_start_matches('#');
+        let matches = |task: &TrackedTask| match target.parse::<u64>() {
+            Ok(id) => task.id == id,
+            Err(_) => target == "all" || task.kind.name() == target,
+        };
+
+        let mut cancelled = Vec::new();
+        self.tasks.retain(|task| {
+            if !matches(task) {
+                return true;
+            }
+            task.handle.abort();
+            cancelled.push(TaskSummary {
+                id: task.id,
+                kind: task.kind,
+                detail: task.detail.clone(),
+                elapsed: task.started.elapsed(),
+            });
+            false
+        });
+        cancelled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending() -> JoinHandle<()> {
+        tokio::spawn(std::future::pending())
+    }
+
+    #[tokio::test]
+    async fn test_cancel_by_id_kind_and_all() {
+        let mut tasks = AnalysisTasks::default();
+        let first = tasks.track(AnalysisKind::Coordination, "split parser work", pending());
+        tasks.track(AnalysisKind::ToolSafety, "Bash", pending());
+        tasks.track(AnalysisKind::ToolSafety, "WebFetch", pending());
+        assert_eq!(tasks.running_count(), 3);
+
+        let cancelled = tasks.cancel(&format!("#{}", first));
+        assert_eq!(cancelled.len(), 1);
+        assert_eq!(cancelled[0].kind, AnalysisKind::Coordination);
+
+        assert_eq!(tasks.cancel("tool-safety").len(), 2);
+        assert!(tasks.cancel("all").is_empty());
+        assert_eq!(tasks.running_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_finished_tasks_are_pruned() {
+        let mut tasks = AnalysisTasks::default();
+        let done = tokio::spawn(async {});
+        tasks.track(AnalysisKind::Automode, "answer question", done);
+        tokio::task::yield_now().await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(tasks.running().is_empty());
+    }
+}
@@ -0,0 +1,169 @@
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Span;
+
+const ESC: char = '\u{1b}';
+
+/// Remove ANSI escape sequences (CSI, OSC and two-byte escapes) and any other
+/// control characters except newlines and tabs.
+pub fn strip_ansi(text: &str) -> String {
+    parse_ansi(text, Style::default())
+        .into_iter()
+        .map(|(segment, _)| segment)
+        .collect()
+}
+
+/// Convert text containing ANSI SGR color codes into styled spans on top of `base`.
+/// Non-SGR escape sequences are dropped, as are stray control characters.
+pub fn ansi_to_spans(text: &str, base: Style) -> Vec<Span<'static>> {
+    parse_ansi(text, base)
+        .into_iter()
+        .map(|(segment, style)| Span::styled(segment, style))
+        .collect()
+}
+
+fn parse_ansi(text: &str, base: Style) -> Vec<(String, Style)> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut style = base;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == ESC {
+            match chars.peek() {
+                Some('[') => {
+                    chars.next();
+                    // CSI: parameter bytes up to a final byte in 0x40..=0x7e
+                    let mut params = String::new();
+                    let mut final_byte = None;
+                    for next in chars.by_ref() {
+                        if ('\u{40}'..='\u{7e}').contains(&next) {
+                            final_byte = Some(next);
+                            break;
+                        }
+                        params.push(next);
+                    }
+                    if final_byte == Some('m') {
+                        if !current.is_empty() {
+                            segments.push((std::mem::take(&mut current), style));
+                        }
+                        style = apply_sgr(style, base, &params);
+                    }
+                }
+                Some(']') => {
+                    // OSC: runs until BEL or ESC \
+                    chars.next();
+                    while let Some(next) = chars.next() {
+                        if next == '\u{7}' {
+                            break;
+                        }
+                        if next == ESC && chars.peek() == Some(&'\\') {
+                            chars.next();
+                            break;
+                        }
+                    }
+                }
+                Some(_) => {
+                    // Two-byte escape such as ESC ( B
+                    chars.next();
+                }
+                None => {}
+            }
+        } else if c.is_control() && c != '\n' && c != '\t' {
+            continue;
+        } else {
+            current.push(c);
+        }
+    }
+
+    if !current.is_empty() {
+        segments.push((current, style));
+    }
+    segments
+}
+
+fn apply_sgr(mut style: Style, base: Style, params: &str) -> Style {
+    let codes: Vec<u16> = if params.is_empty() {
+        vec![0]
+    } else {
+        params.split(';').map(|p| p.parse().unwrap_or(0)).collect()
+    };
+
+    let mut i = 0;
+    while i < codes.len() {
+        match codes[i] {
+            0 => style = base,
+            1 => style = style.add_modifier(Modifier::BOLD),
+            2 => style = style.add_modifier(Modifier::DIM),
+            3 => style = style.add_modifier(Modifier::ITALIC),
+            4 => style = style.add_modifier(Modifier::UNDERLINED),
+            7 => style = style.add_modifier(Modifier::REVERSED),
+            22 => style = style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style = style.remove_modifier(Modifier::ITALIC),
+            24 => style = style.remove_modifier(Modifier::UNDERLINED),
+            27 => style = style.remove_modifier(Modifier::REVERSED),
+            code @ 30..=37 => style = style.fg(Color::Indexed((code - 30) as u8)),
+            code @ 90..=97 => style = style.fg(Color::Indexed((code - 90 + 8) as u8)),
+            code @ 40..=47 => style = style.bg(Color::Indexed((code - 40) as u8)),
+            code @ 100..=107 => style = style.bg(Color::Indexed((code - 100 + 8) as u8)),
+            39 => style.fg = base.fg,
+            49 => style.bg = base.bg,
+            code @ (38 | 48) => {
+                // Extended colors: 38;5;n or 38;2;r;g;b
+                let color = match codes.get(i + 1) {
+                    Some(5) => {
+                        let color = codes.get(i + 2).map(|n| Color::Indexed(*n as u8));
+                        i += 2;
+                        color
+                    }
+                    Some(2) => {
+                        let color = match (codes.get(i + 2), codes.get(i + 3), codes.get(i + 4)) {
+                            (Some(r), Some(g), Some(b)) => Some(Color::Rgb(*r as u8, *g as u8, *b as u8)),
+                            _ => None,
+                        };
+                        i += 4;
+                        color
+                    }
+                    _ => None,
+                };
+                if let Some(color) = color {
+                    style = if code == 38 { style.fg(color) } else { style.bg(color) };
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    style
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi_removes_sequences() {
+        let colored = "\u{1b}[1;31merror\u{1b}[0m: expected `;`\n\u{1b}]0;title\u{7}done";
+        assert_eq!(strip_ansi(colored), "error: expected `;`\ndone");
+    }
+
+    #[test]
+    fn test_strip_ansi_drops_other_control_chars() {
+        assert_eq!(strip_ansi("a\u{8}b\tc\r"), "ab\tc");
+    }
+
+    #[test]
+    fn test_ansi_to_spans_applies_colors() {
+        let spans = ansi_to_spans("ok \u{1b}[32mpassed\u{1b}[39m!", Style::default());
+        assert_eq!(spans.len(), 3);
+        assert_eq!(spans[0].content, "ok ");
+        assert_eq!(spans[1].content, "passed");
+        assert_eq!(spans[1].style.fg, Some(Color::Indexed(2)));
+        assert_eq!(spans[2].style.fg, None);
+    }
+
+    #[test]
+    fn test_ansi_to_spans_truecolor() {
+        let spans = ansi_to_spans("\u{1b}[38;2;10;20;30mx", Style::default());
+        assert_eq!(spans[0].style.fg, Some(Color::Rgb(10, 20, 30)));
+    }
+}
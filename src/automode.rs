@@ -0,0 +1,215 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+
+// How many recent questions and answers are remembered when looking for repeats
+const RECENT_EXCHANGES: usize = 6;
+
+/// Why automode was stopped for a slice
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopVerdict {
+    Continue,
+    /// The same question (or answer) came up again without the user stepping in
+    Repeated,
+    /// Too many automatic answers in a row
+    LimitReached,
+}
+
+/// Tracks the automatic question/answer exchanges of one slice since the user last
+/// spoke, so a Claude/DeepSeek back-and-forth can't run forever
+#[derive(Debug, Default)]
+pub struct LoopGuard {
+    questions: VecDeque<u64>,
+    answers: VecDeque<u64>,
+    exchanges: usize,
+}
+
+impl LoopGuard {
+    /// Record a question Claude asked that automode is about to answer
+    pub fn record_question(&mut self, question: &str, max_exchanges: usize) -> LoopVerdict {
+        self.exchanges += 1;
+        if max_exchanges > 0 && self.exchanges > max_exchanges {
+            return LoopVerdict::LimitReached;
+        }
+        Self::record(&mut self.questions, question)
+    }
+
+    /// Record an answer automode is about to send back to Claude
+    pub fn record_answer(&mut self, answer: &str) -> LoopVerdict {
+        Self::record(&mut self.answers, answer)
+    }
+
+    /// Forget all exchanges, e.g. once the user sends a message
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn record(history: &mut VecDeque<u64>, text: &str) -> LoopVerdict {
+        let print = fingerprint(text);
+        if history.contains(&print) {
+            return LoopVerdict::Repeated;
+        }
+        if history.len() == RECENT_EXCHANGES {
+            history.pop_front();
+        }
+        history.push_back(print);
+        LoopVerdict::Continue
+    }
+}
+
+/// The first of `patterns` found in `message`, ignoring case. Automode leaves messages that
+/// match one of the user's patterns (e.g. "shall i proceed") for the user to answer.
+pub fn matching_pattern<'a>(message: &str, patterns: &'a [String]) -> Option<&'a str> {
+    let message = message.to_lowercase();
+    patterns
+        .iter()
+        .map(|pattern| pattern.trim())
+        .find(|pattern| !pattern.is_empty() && message.contains(&pattern.to_lowercase()))
+}
+
+// First-person complaints Claude makes when it can't use a tool, each followed by the tool or
+// what it was trying to do. Bare phrases like "permission denied" are left out: they turn up
+// in quoted OS errors and in prose about policies.
+const PERMISSION_PHRASES: &[&str] = &[
+    "i don't have permission to",
+    "i do not have permission to",
+    "i need permission to",
+    "i need your permission to",
+    "i haven't been granted permission to",
+    "i have not been granted permission to",
+    "i'm not allowed to",
+    "i am not allowed to",
+    "i'm not permitted to",
+    "i am not permitted to",
+    "i need approval to",
+];
+
+// Claude Code's built-in tools, as they'd be named in prose
+const KNOWN_TOOLS: &[&str] = &[
+    "Bash", "Read", "Write", "Edit", "MultiEdit", "Glob", "Grep", "LS",
+    "WebFetch", "WebSearch", "NotebookEdit", "NotebookRead", "TodoWrite", "Task",
+];
+
+// What Claude was trying to do when no tool is named, and the tool that does it
+const ACTION_TOOLS: &[(&str, &str)] = &[
+    ("run", "Bash"),
+    ("execute", "Bash"),
+    ("fetch", "WebFetch"),
+    ("search the web", "WebSearch"),
+    ("create", "Write"),
+    ("write", "Write"),
+    ("edit", "Edit"),
+    ("modify", "Edit"),
+    ("read", "Read"),
+];
+
+/// The tool Claude says in prose it isn't allowed to use ("I don't have permission to run
+/// `cargo test`"), for denials that never showed up as a tracked tool attempt. Only a
+/// first-person complaint naming a tool, or starting with a tool-shaped action, counts.
+pub fn permission_complaint_tool(message: &str) -> Option<String> {
+    message.lines().find_map(complained_about_tool)
+}
+
+fn complained_about_tool(line: &str) -> Option<String> {
+    // The phrases are ASCII, so a byte-wise match always starts on a character boundary
+    let bytes = line.as_bytes();
+    let rest = (0..bytes.len()).find_map(|start| {
+        PERMISSION_PHRASES.iter().find_map(|phrase| {
+            bytes[start..]
+                .get(..phrase.len())
+                .filter(|candidate| candidate.eq_ignore_ascii_case(phrase.as_bytes()))
+                .map(|_| line[start + phrase.len()..].trim_start())
+        })
+    })?;
+
+    // A tool named outright wins, MCP tools included
+    let words: Vec<&str> = rest
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+        .filter(|word| !word.is_empty())
+        .collect();
+    if let Some(tool) = words.iter().find(|word| word.starts_with("mcp__")) {
+        return Some(tool.to_string());
+    }
+    if let Some(tool) = words.iter().find(|word| KNOWN_TOOLS.contains(word)) {
+        return Some(tool.to_string());
+    }
+
+    // Otherwise go by the action the complaint is about, as a whole word
+    let lower = rest.to_lowercase();
+    ACTION_TOOLS
+        .iter()
+        .find(|(action, _)| {
+            lower
+                .strip_prefix(action)
+                .is_some_and(|after| !after.starts_with(char::is_alphanumeric))
+        })
+        .map(|(_, tool)| tool.to_string())
+}
+
+/// Hash of the text's words, ignoring case, punctuation and whitespace, so trivially
+/// reworded repeats still match
+fn fingerprint(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for word in text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()) {
+        word.to_lowercase().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_question_is_detected() {
+        let mut guard = LoopGuard::default();
+        assert_eq!(guard.record_question("Should I use tokio or async-std?", 10), LoopVerdict::Continue);
+        assert_eq!(guard.record_question("Which test framework?", 10), LoopVerdict::Continue);
+        assert_eq!(guard.record_question("should I use Tokio, or async-std", 10), LoopVerdict::Repeated);
+    }
+
+    #[test]
+    fn test_repeated_answer_is_detected() {
+        let mut guard = LoopGuard::default();
+        assert_eq!(guard.record_answer("Use tokio."), LoopVerdict::Continue);
+        assert_eq!(guard.record_answer("Use tokio"), LoopVerdict::Repeated);
+    }
+
+    #[test]
+    fn test_exchange_limit_and_reset() {
+        let mut guard = LoopGuard::default();
+        for i in 0..3 {
+            assert_eq!(guard.record_question(&format!("question {}", i), 3), LoopVerdict::Continue);
+        }
+        assert_eq!(guard.record_question("question 4", 3), LoopVerdict::LimitReached);
+
+        guard.reset();
+        assert_eq!(guard.exchanges, 0);
+        assert_eq!(guard.record_question("question 0", 3), LoopVerdict::Continue);
+    }
+
+    #[test]
+    fn test_permission_complaint_names_the_tool() {
+        assert_eq!(permission_complaint_tool("I don't have permission to use the WebFetch tool.").as_deref(), Some("WebFetch"));
+        assert_eq!(permission_complaint_tool("Sorry - I don't have permission to run `cargo test` here.").as_deref(), Some("Bash"));
+        assert_eq!(permission_complaint_tool("Plan ready.\nI need permission to create src/new.rs first").as_deref(), Some("Write"));
+        assert_eq!(permission_complaint_tool("I'm not allowed to call mcp__github__create_pr yet").as_deref(), Some("mcp__github__create_pr"));
+        assert_eq!(permission_complaint_tool("I am NOT ALLOWED TO modify Cargo.toml").as_deref(), Some("Edit"));
+        assert_eq!(permission_complaint_tool("The tests pass. Should I run the benchmarks too?"), None);
+    }
+
+    #[test]
+    fn test_permission_complaint_ignores_loose_mentions() {
+        assert_eq!(permission_complaint_tool("I need permission, the change is ready to edit"), None);
+        assert_eq!(permission_complaint_tool("`cat /etc/shadow` fails with: Permission denied (os error 13)"), None);
+        assert_eq!(permission_complaint_tool("Users are not allowed to edit other users' posts."), None);
+        assert_eq!(permission_complaint_tool("I'm not allowed to say which approach is better."), None);
+    }
+
+    #[test]
+    fn test_matching_pattern_ignores_case_and_blank_patterns() {
+        let patterns = vec!["".to_string(), "Shall I proceed".to_string(), "want me to".to_string()];
+        assert_eq!(matching_pattern("Done. shall i PROCEED with the migration?", &patterns), Some("Shall I proceed"));
+        assert_eq!(matching_pattern("Which database should I use?", &patterns), None);
+    }
+}
@@ -0,0 +1,215 @@
+use regex::Regex;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// One subtask of a coordination breakdown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Subtask<'a> {
+    pub description: &'a str,
+    pub scope: Option<&'a str>,
+    pub priority: Option<&'a str>,
+}
+
+impl<'a> Subtask<'a> {
+    /// Description, scope and priority of one subtask line. Fields may be separated by `|`,
+    /// dashes or nothing at all and are matched case-insensitively; when no description can
+    /// be picked out, the whole line is the description.
+    pub fn parse(line: &'a str) -> Self {
+        let line = line.trim();
+        let body = subtask_body(line).unwrap_or(line);
+        let markers: Vec<_> = field_marker().captures_iter(body).collect();
+
+        let description_end = markers
+            .first()
+            .map_or(body.len(), |caps| caps.get(0).unwrap().start());
+        let description = match clean(&body[..description_end]) {
+            "" => clean(line),
+            description => description,
+        };
+
+        let (mut scope, mut priority) = (None, None);
+        for (i, caps) in markers.iter().enumerate() {
+            let start = caps.get(0).unwrap().end();
+            let end = markers
+                .get(i + 1)
+                .map_or(body.len(), |next| next.get(0).unwrap().start());
+            let value = Some(clean(&body[start..end])).filter(|value| !value.is_empty());
+            if caps[1].eq_ignore_ascii_case("scope") {
+                scope = scope.or(value);
+            } else {
+                priority = priority.or(value);
+            }
+        }
+        Subtask { description, scope, priority }
+    }
+}
+
+/// `Description | SCOPE: ... | PRIORITY: ...`, leaving out the fields that weren't given
+impl fmt::Display for Subtask<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description)?;
+        if let Some(scope) = self.scope {
+            write!(f, " | SCOPE: {}", scope)?;
+        }
+        if let Some(priority) = self.priority {
+            write!(f, " | PRIORITY: {}", priority)?;
+        }
+        Ok(())
+    }
+}
+
+// "SUBTASK_1:", "**Subtask 2 -**", "- SUBTASK #3." after any bullets, quotes or heading marks
+fn subtask_header() -> &'static Regex {
+    static HEADER: OnceLock<Regex> = OnceLock::new();
+    HEADER.get_or_init(|| {
+        Regex::new(r"(?i)^[\s>#*-]*subtask[\s_#-]*\d+\**\s*[:.)\-–—]?\s*\**\s*").unwrap()
+    })
+}
+
+// "1." or "2)" starting a numbered list item; only a subtask when the line also names a scope
+fn numbered_header() -> &'static Regex {
+    static HEADER: OnceLock<Regex> = OnceLock::new();
+    HEADER.get_or_init(|| Regex::new(r"^[\s>#*-]*\d+[.)]\s+\**\s*").unwrap())
+}
+
+fn field_marker() -> &'static Regex {
+    static FIELD: OnceLock<Regex> = OnceLock::new();
+    FIELD.get_or_init(|| Regex::new(r"(?i)\**\b(scope|priority)\b\**\s*:\**").unwrap())
+}
+
+// What's left of a subtask line after its numbering, or None when it isn't one
+fn subtask_body(line: &str) -> Option<&str> {
+    if let Some(header) = subtask_header().find(line) {
+        return Some(&line[header.end()..]);
+    }
+    let header = numbered_header().find(line)?;
+    let body = &line[header.end()..];
+    field_marker()
+        .captures_iter(body)
+        .any(|caps| caps[1].eq_ignore_ascii_case("scope"))
+        .then_some(body)
+}
+
+fn clean(text: &str) -> &str {
+    text.trim_matches(|c: char| {
+        c.is_whitespace() || matches!(c, '|' | '-' | '–' | '—' | ',' | ';' | '*' | '[' | ']')
+    })
+}
+
+/// The subtasks of a coordination breakdown, in order. Besides the requested
+/// `SUBTASK_N: ...` form this accepts `Subtask N -`, Markdown bullets and bold, and numbered
+/// list items that name a `SCOPE:`.
+pub fn parse_subtasks(breakdown: &str) -> Vec<Subtask<'_>> {
+    breakdown
+        .lines()
+        .map(str::trim)
+        .filter(|line| subtask_body(line).is_some())
+        .map(Subtask::parse)
+        .collect()
+}
+
+/// Whether two subtask scopes may touch the same files. A missing scope covers everything;
+/// otherwise the comma-separated paths overlap when one is, or lies inside, the other.
+pub fn scopes_overlap(a: Option<&str>, b: Option<&str>) -> bool {
+    let (Some(a), Some(b)) = (a, b) else {
+        return true;
+    };
+    let paths = |scope: &str| -> Vec<String> {
+        scope
+            .split(',')
+            .map(|path| path.trim().trim_start_matches("./").trim_end_matches('/').to_lowercase())
+            .filter(|path| !path.is_empty())
+            .collect()
+    };
+    let contains = |outer: &str, inner: &str| {
+        inner == outer || inner.strip_prefix(outer).is_some_and(|rest| rest.starts_with('/'))
+    };
+    let (a, b) = (paths(a), paths(b));
+    a.iter()
+        .any(|a| b.iter().any(|b| contains(a, b) || contains(b, a)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(breakdown: &str) -> Vec<(&str, Option<&str>, Option<&str>)> {
+        parse_subtasks(breakdown)
+            .into_iter()
+            .map(|subtask| (subtask.description, subtask.scope, subtask.priority))
+            .collect()
+    }
+
+    #[test]
+    fn test_parses_common_ollama_formats() {
+        let canonical =
+            "Response:\nSUBTASK_1: Build the parser | SCOPE: src/parser.rs | PRIORITY: High\n\
+            SUBTASK_2: Write docs | SCOPE: docs/";
+        assert_eq!(
+            parsed(canonical),
+            vec![
+                ("Build the parser", Some("src/parser.rs"), Some("High")),
+                ("Write docs", Some("docs/"), None),
+            ]
+        );
+
+        let dashes =
+            "SUBTASK_1 - Add the API routes - Scope: src/api, src/routes.rs - Priority: Medium\n\
+            Subtask 2: Frontend forms|scope:[web/forms]|priority:low";
+        assert_eq!(
+            parsed(dashes),
+            vec![
+                (
+                    "Add the API routes",
+                    Some("src/api, src/routes.rs"),
+                    Some("Medium")
+                ),
+                ("Frontend forms", Some("web/forms"), Some("low")),
+            ]
+        );
+
+        let markdown = "Here's the split:\n\n- **SUBTASK_1:** Migrate the schema | **SCOPE:** migrations/ | **PRIORITY:** High\n\
+            * **Subtask 2** — Seed data\n\
+            1. Update the CLI | SCOPE: src/cli.rs | PRIORITY: Low\n\
+            2. This numbered line has no scope so it is prose";
+        assert_eq!(
+            parsed(markdown),
+            vec![
+                ("Migrate the schema", Some("migrations/"), Some("High")),
+                ("Seed data", None, None),
+                ("Update the CLI", Some("src/cli.rs"), Some("Low")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_falls_back_to_whole_line_and_finds_nothing_in_prose() {
+        assert_eq!(
+            parsed("SUBTASK_3: | SCOPE: tests/"),
+            vec![("SUBTASK_3: | SCOPE: tests/", Some("tests/"), None)]
+        );
+        assert!(parse_subtasks("SINGLE_INSTANCE_SUFFICIENT: the task is small").is_empty());
+        assert!(
+            parse_subtasks("I'd split this into subtasks for the parser and the docs.").is_empty()
+        );
+        assert!(parse_subtasks("").is_empty());
+    }
+
+    #[test]
+    fn test_subtask_displays_in_breakdown_form() {
+        let subtasks = parse_subtasks(
+            "- **Subtask 1** — Add routes - Scope: src/api - Priority: High\n2. Docs | SCOPE: docs/",
+        );
+        assert_eq!(subtasks[0].to_string(), "Add routes | SCOPE: src/api | PRIORITY: High");
+        assert_eq!(subtasks[1].to_string(), "Docs | SCOPE: docs/");
+    }
+
+    #[test]
+    fn test_scopes_overlap_when_paths_nest() {
+        assert!(scopes_overlap(Some("src/api"), Some("./src/api/routes.rs")));
+        assert!(scopes_overlap(Some("docs/, src/cli.rs"), Some("src/cli.rs")));
+        assert!(scopes_overlap(None, Some("tests/")));
+        assert!(!scopes_overlap(Some("src/api"), Some("src/api_client.rs")));
+        assert!(!scopes_overlap(Some("migrations/"), Some("web/forms")));
+    }
+}
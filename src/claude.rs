@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::process::Stdio;
 use std::sync::Arc;
 use tokio::sync::mpsc;
@@ -7,15 +8,27 @@ use uuid::Uuid;
 use anyhow::Result;
 use serde::Deserialize;
 
+// How often a finished Claude process is checked for while its turn is running
+const EXIT_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Oldest `claude` CLI Veda is known to read correctly; earlier ones predate the
+/// `--output-format stream-json --verbose` events it parses
+pub const MIN_CLAUDE_VERSION: (u64, u64, u64) = (1, 0, 0);
+
 #[derive(Debug, Clone)]
 pub enum ClaudeMessage {
     StreamStart { session_id: Option<String> },
     StreamText { text: String, session_id: Option<String> },
+    StreamThinking { text: String, session_id: Option<String> },
     StreamEnd { session_id: Option<String> },
     SystemMessage { text: String, session_id: Option<String> },
     Error { error: String, session_id: Option<String> },
     Exited { code: Option<i32>, session_id: Option<String> },
-    ToolUse { tool_name: String, session_id: Option<String> },
+    /// `file_path` is the file an editing tool (Edit, Write, ...) is about to change;
+    /// `tool_use_id` matches the call up with its `ToolResult`
+    ToolUse { tool_name: String, tool_use_id: String, file_path: Option<String>, session_id: Option<String> },
+    /// How the tool call `tool_use_id` turned out; `is_error` covers denied permissions too
+    ToolResult { tool_use_id: String, is_error: bool, session_id: Option<String> },
     SessionStarted { session_id: String, target_tab_id: Option<uuid::Uuid> },
     ToolPermissionDenied { tool_name: String, session_id: Option<String> },
     ToolApproved { tool_name: String, session_id: Option<String> },
@@ -23,6 +36,8 @@ pub enum ClaudeMessage {
     VedaSpawnInstances { task_description: String, num_instances: u8, session_id: String },
     VedaListInstances { session_id: String },
     VedaCloseInstance { session_id: String },
+    VedaSetMaxInstances { max_instances: usize, session_id: String },
+    VedaSendMessage { session_id: String, to: String, text: String },
     // Internal message for background coordination
     InternalCoordinateInstances { 
         main_instance_id: Uuid, 
@@ -31,10 +46,18 @@ pub enum ClaudeMessage {
         working_dir: String,
         is_ipc: bool,
     },
+    // Breakdown for `!recoordinate` to reassign across the existing slices, or why it failed
+    InternalRecoordinate { main_instance_id: Uuid, breakdown: Result<String, String> },
     // Inter-Veda coordination message
     CoordinationMessage { 
         message: crate::shared_ipc::VedaCoordinationMessage,
     },
+    // System message for a specific slice, by tab id (falls back to the main slice)
+    InstanceSystemMessage { instance_id: Uuid, text: String },
+    // Piece of a streamed task breakdown for a slice's transient progress message; None once it's done
+    AnalysisProgress { instance_id: Uuid, chunk: Option<String> },
+    // Seconds left in a coordination's grace period; 0 once the breakdown starts
+    CoordinationCountdown { instance_id: Uuid, secs_left: u64 },
     // Process handle update for tool auto-approval
     ProcessHandleUpdate {
         session_id: Option<String>,
@@ -82,6 +105,9 @@ pub struct AssistantMessage {
 pub enum ContentItem {
     #[serde(rename = "text")]
     Text { text: String },
+    /// Extended thinking, when the CLI surfaces it
+    #[serde(rename = "thinking")]
+    Thinking { thinking: String },
     #[serde(rename = "tool_use")]
     ToolUse {
         id: String,
@@ -145,6 +171,58 @@ pub async fn enable_claude_tool(tool_name: &str) -> Result<()> {
     Ok(())
 }
 
+/// `claude --version`, e.g. "1.0.35 (Claude Code)"
+pub async fn claude_cli_version() -> Result<String> {
+    let output = AsyncCommand::new("claude").arg("--version").output().await?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("claude --version failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The file an editing tool call changes, from its input; `None` for tools that don't edit files
+pub fn edited_file(tool_name: &str, input: &serde_json::Value) -> Option<String> {
+    let key = match tool_name {
+        "Edit" | "MultiEdit" | "Write" => "file_path",
+        "NotebookEdit" => "notebook_path",
+        _ => return None,
+    };
+    input.get(key).and_then(|v| v.as_str()).filter(|path| !path.is_empty()).map(str::to_string)
+}
+
+/// Warning for a `claude --version` output Veda can't vouch for: older than
+/// `MIN_CLAUDE_VERSION`, or without a version number at all
+pub fn claude_version_warning(version_output: &str) -> Option<String> {
+    let number = version_output.split_whitespace().next().unwrap_or("");
+    let parts: Vec<u64> = number.split('.').map_while(|part| part.parse().ok()).collect();
+    let (major, minor, patch) = MIN_CLAUDE_VERSION;
+    match parts.as_slice() {
+        [a, b, c, ..] if (*a, *b, *c) >= MIN_CLAUDE_VERSION => None,
+        [_, _, _, ..] => Some(format!(
+            "⚠️ Claude CLI {} is older than {}.{}.{} and may not produce the stream-json output Veda reads, so replies could go missing. Update it (e.g. `claude update`).",
+            number, major, minor, patch
+        )),
+        _ => Some(format!(
+            "⚠️ Couldn't read a version from `claude --version` ({}). If replies don't appear, check that `claude` is the Claude Code CLI, {}.{}.{} or newer.",
+            version_output, major, minor, patch
+        )),
+    }
+}
+
+/// Whether a line of `claude` output can't be a stream-json event at all
+fn is_not_stream_json(line: &str) -> bool {
+    !matches!(serde_json::from_str::<serde_json::Value>(line), Ok(serde_json::Value::Object(_)))
+}
+
+/// Error shown when `claude` writes something other than stream-json, which Veda can't display
+pub fn unexpected_output_error(line: &str) -> String {
+    let sample: String = line.chars().take(200).collect();
+    format!(
+        "❌ Claude's output isn't the stream-json Veda expects, so its replies can't be shown. Check `claude --version` (Veda needs {}.{}.{} or newer). It printed: {}",
+        MIN_CLAUDE_VERSION.0, MIN_CLAUDE_VERSION.1, MIN_CLAUDE_VERSION.2, sample
+    )
+}
+
 pub async fn send_to_claude(
     message: String,
     tx: mpsc::Sender<ClaudeMessage>,
@@ -175,14 +253,48 @@ impl ClaudeStreamEvent {
         }
         None
     }
+    
+    /// The `tool_result`s in a user message: which tool call each answers and whether it failed
+    pub fn tool_results(message: &serde_json::Value) -> Vec<(String, bool)> {
+        let Some(content_array) = message.get("content").and_then(|c| c.as_array()) else {
+            return Vec::new();
+        };
+        content_array.iter()
+            .filter(|item| item.get("type").and_then(|t| t.as_str()) == Some("tool_result"))
+            .filter_map(|item| {
+                let id = item.get("tool_use_id")?.as_str()?;
+                let is_error = item.get("is_error").and_then(|e| e.as_bool()).unwrap_or(false);
+                Some((id.to_string(), is_error))
+            })
+            .collect()
+    }
 }
 
+// Kept for library users and integration tests; the app goes through `ClaudeClient`
+#[allow(dead_code)]
 pub async fn send_to_claude_with_session(
     message: String,
     tx: mpsc::Sender<ClaudeMessage>,
     session_id: Option<String>,
     process_handle_storage: Option<Arc<tokio::sync::Mutex<Option<tokio::process::Child>>>>,
     target_tab_id: Option<uuid::Uuid>,
+) -> Result<()> {
+    send_to_claude_with_env(message, tx, session_id, process_handle_storage, target_tab_id, HashMap::new(), None, None).await
+}
+
+/// Like `send_to_claude_with_session`, but with extra environment variables set on the
+/// spawned `claude` process (and therefore on the tools it runs), an optional `--model` and
+/// the directory it starts in (None inherits Veda's)
+#[allow(clippy::too_many_arguments)]
+pub async fn send_to_claude_with_env(
+    message: String,
+    tx: mpsc::Sender<ClaudeMessage>,
+    session_id: Option<String>,
+    process_handle_storage: Option<Arc<tokio::sync::Mutex<Option<tokio::process::Child>>>>,
+    target_tab_id: Option<uuid::Uuid>,
+    env: HashMap<String, String>,
+    model: Option<String>,
+    working_dir: Option<String>,
 ) -> Result<()> {
     tracing::info!("send_to_claude_with_session called with message: {} (session: {:?})", message, session_id);
     
@@ -194,6 +306,12 @@ pub async fn send_to_claude_with_session(
         cmd.env("VEDA_SESSION_ID", veda_session_id);
     }
     
+    // Slice-local overrides; values are deliberately not logged since they may be secrets
+    if !env.is_empty() {
+        tracing::info!("Applying slice environment overrides: {:?}", env.keys().collect::<Vec<_>>());
+        cmd.envs(&env);
+    }
+    
     // For new conversations, start without session ID to get one from Claude
     // For resuming, use the provided session ID
     // We eliminate instance_id from the flow entirely
@@ -202,6 +320,14 @@ pub async fn send_to_claude_with_session(
     if let Some(session) = session_id {
         cmd.arg("--resume").arg(session);
     }
+    if let Some(model) = &model {
+        tracing::info!("Using Claude model {}", model);
+        cmd.arg("--model").arg(model);
+    }
+    // Sessions belong to the directory they were started in, so resume them from there too
+    if let Some(dir) = &working_dir {
+        cmd.current_dir(dir);
+    }
     
     cmd.arg("-p")
         .arg(&message)
@@ -246,14 +372,21 @@ pub async fn send_to_claude_with_session(
         let reader = BufReader::new(stdout);
         let mut lines = reader.lines();
         let mut line_count = 0;
+        // Events understood so far; none at all means the CLI isn't speaking stream-json
+        let mut event_count = 0;
+        let mut reported_format = false;
         
         while let Ok(Some(line)) = lines.next_line().await {
             line_count += 1;
             tracing::debug!("STDOUT line {}: {}", line_count, line);
+            if line.trim().is_empty() {
+                continue;
+            }
             
             // Parse JSON streaming events
             match serde_json::from_str::<ClaudeStreamEvent>(&line) {
                 Ok(event) => {
+                    event_count += 1;
                     tracing::debug!("Parsed event: {:?}", event);
                     match event {
                         ClaudeStreamEvent::System { subtype, session_id } => {
@@ -278,7 +411,14 @@ pub async fn send_to_claude_with_session(
                                             session_id: Some(session_id.clone()),
                                         }).await;
                                     }
-                                    ContentItem::ToolUse { name, input, .. } => {
+                                    ContentItem::Thinking { thinking } => {
+                                        tracing::debug!("Received {} chars of thinking for session {}", thinking.len(), session_id);
+                                        let _ = tx_stdout.send(ClaudeMessage::StreamThinking {
+                                            text: thinking,
+                                            session_id: Some(session_id.clone()),
+                                        }).await;
+                                    }
+                                    ContentItem::ToolUse { id, name, input } => {
                                         tracing::info!("Claude attempting to use tool: {}", name);
                                         
                                         // Check for Veda instance management MCP calls
@@ -313,7 +453,9 @@ pub async fn send_to_claude_with_session(
                                             _ => {
                                                 // Regular tool use
                                                 let _ = tx_stdout.send(ClaudeMessage::ToolUse {
+                                                    file_path: edited_file(&name, &input),
                                                     tool_name: name,
+                                                    tool_use_id: id,
                                                     session_id: Some(session_id.clone()),
                                                 }).await;
                                             }
@@ -349,19 +491,39 @@ pub async fn send_to_claude_with_session(
                                 tracing::info!("Tool permission denied for: {}", tool_name);
                                 let _ = tx_stdout.send(ClaudeMessage::ToolPermissionDenied {
                                     tool_name,
-                                    session_id: Some(session_id),
+                                    session_id: Some(session_id.clone()),
+                                }).await;
+                            }
+                            for (tool_use_id, is_error) in ClaudeStreamEvent::tool_results(&message) {
+                                let _ = tx_stdout.send(ClaudeMessage::ToolResult {
+                                    tool_use_id,
+                                    is_error,
+                                    session_id: Some(session_id.clone()),
                                 }).await;
-                            } else {
-                                tracing::debug!("Ignoring User event (no permission issue detected)");
                             }
                         }
                     }
                 }
+                Err(e) if event_count == 0 && !reported_format && is_not_stream_json(&line) => {
+                    tracing::error!("Claude output isn't stream-json: {} - Error: {}", line, e);
+                    reported_format = true;
+                    let _ = tx_stdout.send(ClaudeMessage::Error {
+                        error: unexpected_output_error(&line),
+                        session_id: session_id_clone.clone(),
+                    }).await;
+                }
                 Err(e) => {
                     tracing::warn!("Failed to parse JSON line: {} - Error: {}", line, e);
                 }
             }
         }
+        if line_count > 0 && event_count == 0 && !reported_format {
+            tracing::error!("None of Claude's {} output lines were events Veda understands", line_count);
+            let _ = tx_stdout.send(ClaudeMessage::Error {
+                error: unexpected_output_error("(JSON, but no events Veda recognizes)"),
+                session_id: session_id_clone.clone(),
+            }).await;
+        }
         tracing::info!("Stdout reader task finished after {} lines", line_count);
     });
 
@@ -400,15 +562,13 @@ pub async fn send_to_claude_with_session(
         tracing::debug!("Waiting for claude process to exit for session {:?}", session_id_exit);
         
         let wait_result = if let Some(handle_storage) = handle_storage_clone {
-            // Wait using the stored process handle
-            let mut handle_guard = handle_storage.lock().await;
-            if let Some(ref mut stored_cmd) = handle_guard.as_mut() {
-                let result = stored_cmd.wait().await;
-                drop(handle_guard);
-                result
-            } else {
-                drop(handle_guard);
-                Err(std::io::Error::new(std::io::ErrorKind::Other, "No process handle available"))
+            match wait_for_exit(&handle_storage, process_pid).await {
+                Some(result) => result,
+                None => {
+                    // Whoever took or replaced the process owns the slice's state now
+                    tracing::debug!("Process {:?} for session {:?} left its slot, stopping exit watch", process_pid, session_id_exit);
+                    return;
+                }
             }
         } else {
             // No process handle storage was provided, which means we can't wait for completion
@@ -439,10 +599,42 @@ pub async fn send_to_claude_with_session(
     Ok(())
 }
 
+/// Wait for the process with `pid` stored in `handle` to exit. The handle is polled rather
+/// than locked across `wait()`, so interrupting or closing the slice can still reach the child.
+/// `None` once the slot no longer holds that process: an interrupt or close took or reaped it,
+/// or the slice's next turn replaced it.
+pub async fn wait_for_exit(
+    handle: &Arc<tokio::sync::Mutex<Option<tokio::process::Child>>>,
+    pid: Option<u32>,
+) -> Option<std::io::Result<std::process::ExitStatus>> {
+    loop {
+        let status = match handle.lock().await.as_mut() {
+            Some(child) if child.id() == pid => child.try_wait(),
+            _ => return None,
+        };
+        match status {
+            Ok(Some(status)) => return Some(Ok(status)),
+            Ok(None) => tokio::time::sleep(EXIT_POLL_INTERVAL).await,
+            Err(e) => return Some(Err(e)),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_thinking_content() {
+        let json = r#"{"type":"assistant","message":{"id":"msg_1","content":[{"type":"thinking","thinking":"Check the tests first.","signature":"abc"},{"type":"text","text":"Done"}]},"session_id":"sess_1"}"#;
+        let event = serde_json::from_str::<ClaudeStreamEvent>(json).unwrap();
+        let ClaudeStreamEvent::Assistant { message, .. } = event else {
+            panic!("Expected Assistant event");
+        };
+        assert_eq!(message.content[0], ContentItem::Thinking { thinking: "Check the tests first.".to_string() });
+        assert_eq!(message.content[1], ContentItem::Text { text: "Done".to_string() });
+    }
+
     #[test]
     fn test_parse_assistant_message() {
         let json = r#"{"type":"assistant","message":{"id":"msg_123","content":[{"type":"text","text":"Hello, world!"}]},"session_id":"sess_123"}"#;
@@ -488,6 +680,60 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_edited_file() {
+        let input = serde_json::json!({"file_path": "/repo/src/main.rs", "old_string": "a", "new_string": "b"});
+        assert_eq!(edited_file("Edit", &input).as_deref(), Some("/repo/src/main.rs"));
+        assert_eq!(edited_file("MultiEdit", &input).as_deref(), Some("/repo/src/main.rs"));
+        assert_eq!(edited_file("Read", &input), None);
+        assert_eq!(edited_file("NotebookEdit", &serde_json::json!({"notebook_path": "/repo/a.ipynb"})).as_deref(), Some("/repo/a.ipynb"));
+        assert_eq!(edited_file("Write", &serde_json::json!({"content": "x"})), None);
+    }
+
+    #[test]
+    fn test_tool_results() {
+        let message = serde_json::json!({"role": "user", "content": [
+            {"type": "tool_result", "tool_use_id": "toolu_1", "content": "The file has been updated."},
+            {"type": "tool_result", "tool_use_id": "toolu_2", "content": "Permission denied", "is_error": true},
+            {"type": "text", "text": "not a result"},
+        ]});
+        assert_eq!(ClaudeStreamEvent::tool_results(&message), [("toolu_1".to_string(), false), ("toolu_2".to_string(), true)]);
+        assert!(ClaudeStreamEvent::tool_results(&serde_json::json!({"content": "plain"})).is_empty());
+    }
+
+    #[test]
+    fn test_claude_version_warning() {
+        assert_eq!(claude_version_warning("1.0.35 (Claude Code)"), None);
+        assert_eq!(claude_version_warning("2.1.0"), None);
+        assert!(claude_version_warning("0.2.9 (Claude Code)").unwrap().contains("0.2.9 is older than 1.0.0"));
+        assert!(claude_version_warning("Claude Code").unwrap().starts_with("⚠️ Couldn't read a version"));
+    }
+
+    #[test]
+    fn test_plain_text_output_is_not_stream_json() {
+        assert!(is_not_stream_json("Hello! How can I help?"));
+        assert!(is_not_stream_json("42"));
+        assert!(!is_not_stream_json(r#"{"type":"stream_event","event":{}}"#));
+        assert!(unexpected_output_error("Hello!").ends_with("It printed: Hello!"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_wait_for_exit_stops_once_the_process_leaves_its_slot() {
+        let handle = Arc::new(tokio::sync::Mutex::new(None));
+        let child = AsyncCommand::new("true").spawn().unwrap();
+        let pid = child.id();
+        *handle.lock().await = Some(child);
+        assert!(wait_for_exit(&handle, pid).await.unwrap().unwrap().success());
+        
+        // Already reaped, replaced by the next turn's process, or taken out of the slot
+        assert!(wait_for_exit(&handle, pid).await.is_none());
+        *handle.lock().await = Some(AsyncCommand::new("sleep").arg("30").kill_on_drop(true).spawn().unwrap());
+        assert!(wait_for_exit(&handle, pid).await.is_none());
+        *handle.lock().await = None;
+        assert!(wait_for_exit(&handle, pid).await.is_none());
+    }
+
     #[tokio::test]
     async fn test_claude_message_channel() {
         let (tx, mut rx) = mpsc::channel(10);
@@ -0,0 +1,479 @@
+use anyhow::Result;
+use futures_util::future::BoxFuture;
+use rand::Rng;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::claude::{claude_cli_version, send_to_claude_with_env, ClaudeMessage};
+use crate::config::VedaConfig;
+
+/// Shared slot holding the running `claude` child for a slice, so it can be interrupted
+pub type ProcessHandle = Arc<tokio::sync::Mutex<Option<tokio::process::Child>>>;
+
+/// Environment variable that swaps the real Claude and Ollama clients for the mocks below
+pub const TEST_MODE_ENV: &str = "VEDA_TEST_MODE";
+
+/// Ollama model behind the analysis client
+pub const OLLAMA_MODEL: &str = "gemma3:12b";
+
+/// Local model used for automode decisions (coordination, tool safety, task breakdowns)
+pub trait AnalysisClient: Send + Sync {
+    /// Answer a short analysis prompt, e.g. whether a task benefits from coordination
+    fn analyze(&self, prompt: String) -> BoxFuture<'static, Result<String>>;
+    /// Break a task into `SUBTASK_N:` lines for spawning slices
+    fn plan(&self, prompt: String) -> BoxFuture<'static, Result<String>>;
+    /// `plan`, also sending the breakdown to `progress` piece by piece as it's written
+    fn plan_streaming(&self, prompt: String, _progress: mpsc::UnboundedSender<String>) -> BoxFuture<'static, Result<String>> {
+        self.plan(prompt)
+    }
+    /// Load the model ahead of the first real request, so that one doesn't pay for it
+    fn warm_up(&self) -> BoxFuture<'static, Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Everything needed to start or resume one Claude turn
+#[derive(Debug, Clone, Default)]
+pub struct ClaudeRequest {
+    pub message: String,
+    pub session_id: Option<String>,
+    pub process_handle: Option<ProcessHandle>,
+    pub target_tab_id: Option<Uuid>,
+    pub env: HashMap<String, String>,
+    /// `--model` for the `claude` CLI; None keeps its default
+    pub model: Option<String>,
+    /// Directory the `claude` process starts in; None inherits Veda's
+    pub working_dir: Option<String>,
+}
+
+/// Starts Claude turns; replies come back as `ClaudeMessage`s on `tx`
+pub trait ClaudeClient: Send + Sync {
+    fn send(&self, request: ClaudeRequest, tx: mpsc::Sender<ClaudeMessage>) -> BoxFuture<'static, Result<()>>;
+    /// What `claude --version` reports, checked once at startup
+    fn version(&self) -> BoxFuture<'static, Result<String>>;
+}
+
+/// Pick real clients, or mocks when `VEDA_TEST_MODE` is set
+pub fn default_clients(config: &VedaConfig) -> (Arc<dyn AnalysisClient>, Arc<dyn ClaudeClient>) {
+    if std::env::var_os(TEST_MODE_ENV).is_some() {
+        tracing::warn!("{} is set - Claude and Ollama are stubbed out", TEST_MODE_ENV);
+        (Arc::new(MockAnalysisClient::new("SINGLE_INSTANCE_SUFFICIENT: test mode")), Arc::new(MockClaudeClient::default()))
+    } else {
+        (Arc::new(OllamaAnalysisClient { retry: RetryPolicy::from_config(config) }), Arc::new(CliClaudeClient))
+    }
+}
+
+/// Talks to the local Ollama server
+pub struct OllamaAnalysisClient {
+    pub retry: RetryPolicy,
+}
+
+impl AnalysisClient for OllamaAnalysisClient {
+    fn analyze(&self, prompt: String) -> BoxFuture<'static, Result<String>> {
+        let retry = self.retry;
+        Box::pin(async move { ollama_analyze(&prompt, retry).await })
+    }
+
+    fn plan(&self, prompt: String) -> BoxFuture<'static, Result<String>> {
+        let retry = self.retry;
+        Box::pin(async move { ollama_plan(&prompt, retry, None).await })
+    }
+
+    fn plan_streaming(&self, prompt: String, progress: mpsc::UnboundedSender<String>) -> BoxFuture<'static, Result<String>> {
+        let retry = self.retry;
+        Box::pin(async move { ollama_plan(&prompt, retry, Some(&progress)).await })
+    }
+
+    fn warm_up(&self) -> BoxFuture<'static, Result<()>> {
+        let retry = self.retry;
+        Box::pin(async move { ollama_warm_up(retry).await })
+    }
+}
+
+/// Spawns the `claude` CLI in stream-json mode
+pub struct CliClaudeClient;
+
+impl ClaudeClient for CliClaudeClient {
+    fn send(&self, request: ClaudeRequest, tx: mpsc::Sender<ClaudeMessage>) -> BoxFuture<'static, Result<()>> {
+        Box::pin(send_to_claude_with_env(
+            request.message,
+            tx,
+            request.session_id,
+            request.process_handle,
+            request.target_tab_id,
+            request.env,
+            request.model,
+            request.working_dir,
+        ))
+    }
+
+    fn version(&self) -> BoxFuture<'static, Result<String>> {
+        Box::pin(claude_cli_version())
+    }
+}
+
+/// Analysis client with canned answers. Queued responses are used first, then the
+/// default; every prompt is recorded.
+pub struct MockAnalysisClient {
+    default_response: String,
+    responses: Mutex<VecDeque<String>>,
+    prompts: Arc<Mutex<Vec<String>>>,
+}
+
+// Inspection helpers are only called from tests
+#[allow(dead_code)]
+impl MockAnalysisClient {
+    pub fn new(default_response: &str) -> Self {
+        Self {
+            default_response: default_response.to_string(),
+            responses: Mutex::new(VecDeque::new()),
+            prompts: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Answer the next prompt with `response` instead of the default
+    pub fn push_response(&self, response: &str) {
+        self.responses.lock().unwrap().push_back(response.to_string());
+    }
+
+    pub fn prompts(&self) -> Vec<String> {
+        self.prompts.lock().unwrap().clone()
+    }
+
+    fn respond(&self, prompt: String) -> BoxFuture<'static, Result<String>> {
+        self.prompts.lock().unwrap().push(prompt);
+        let response = self.responses.lock().unwrap().pop_front().unwrap_or_else(|| self.default_response.clone());
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+impl AnalysisClient for MockAnalysisClient {
+    fn analyze(&self, prompt: String) -> BoxFuture<'static, Result<String>> {
+        self.respond(prompt)
+    }
+
+    fn plan(&self, prompt: String) -> BoxFuture<'static, Result<String>> {
+        self.respond(prompt)
+    }
+
+    fn plan_streaming(&self, prompt: String, progress: mpsc::UnboundedSender<String>) -> BoxFuture<'static, Result<String>> {
+        let response = self.respond(prompt);
+        Box::pin(async move {
+            let response = response.await?;
+            let _ = progress.send(response.clone());
+            Ok(response)
+        })
+    }
+}
+
+/// Claude client that records requests and answers each one with a short canned
+/// stream, starting a `mock-session-N` session for requests without one
+#[derive(Default)]
+pub struct MockClaudeClient {
+    requests: Arc<Mutex<Vec<ClaudeRequest>>>,
+}
+
+#[allow(dead_code)]
+impl MockClaudeClient {
+    pub fn requests(&self) -> Vec<ClaudeRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl ClaudeClient for MockClaudeClient {
+    fn send(&self, request: ClaudeRequest, tx: mpsc::Sender<ClaudeMessage>) -> BoxFuture<'static, Result<()>> {
+        let session_id = {
+            let mut requests = self.requests.lock().unwrap();
+            requests.push(request.clone());
+            request.session_id.clone().unwrap_or_else(|| format!("mock-session-{}", requests.len()))
+        };
+        Box::pin(async move {
+            if request.session_id.is_none() {
+                tx.send(ClaudeMessage::SessionStarted {
+                    session_id: session_id.clone(),
+                    target_tab_id: request.target_tab_id,
+                }).await?;
+            }
+            let session_id = Some(session_id);
+            tx.send(ClaudeMessage::StreamStart { session_id: session_id.clone() }).await?;
+            tx.send(ClaudeMessage::StreamText {
+                text: format!("[test mode] received {} chars", request.message.chars().count()),
+                session_id: session_id.clone(),
+            }).await?;
+            tx.send(ClaudeMessage::StreamEnd { session_id }).await?;
+            Ok(())
+        })
+    }
+
+    fn version(&self) -> BoxFuture<'static, Result<String>> {
+        Box::pin(async { Ok("1.0.0 (test mode)".to_string()) })
+    }
+}
+
+/// How Ollama requests are retried: `max_retries` attempts after the first, waiting
+/// `base_delay` doubled on each retry (capped at 30s, plus up to a second of jitter)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+}
+
+impl RetryPolicy {
+    const MAX_DELAY: Duration = Duration::from_secs(30);
+
+    pub fn from_config(config: &VedaConfig) -> Self {
+        Self {
+            max_retries: config.ollama_max_retries,
+            base_delay: Duration::from_millis(config.ollama_retry_base_delay_ms),
+        }
+    }
+
+    /// Backoff before retry number `attempt` (1-based), without jitter
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(Self::MAX_DELAY)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::from_config(&VedaConfig::default())
+    }
+}
+
+/// One-shot Ollama completion used for coordination decisions and tool safety checks
+async fn ollama_analyze(prompt: &str, retry: RetryPolicy) -> Result<String> {
+    let request_body = serde_json::json!({
+        "model": OLLAMA_MODEL,
+        "prompt": prompt,
+        "stream": false
+    });
+    ollama_generate(&request_body, Duration::from_secs(120), retry, None).await
+}
+
+/// Ollama completion tuned for task breakdowns: terse, low temperature, short timeout.
+/// With `progress` the answer is streamed and each piece forwarded as it arrives.
+async fn ollama_plan(prompt: &str, retry: RetryPolicy, progress: Option<&mpsc::UnboundedSender<String>>) -> Result<String> {
+    // Try with optimized prompt for faster response
+    let optimized_prompt = format!(
+        "{}\n\nIMPORTANT: Respond ONLY in the requested format. Skip chain-of-thought. Be direct.",
+        prompt
+    );
+    
+    let request_body = serde_json::json!({
+        "model": OLLAMA_MODEL,
+        "prompt": optimized_prompt,
+        "stream": progress.is_some(),
+        "options": {
+            "temperature": 0.1,
+            "top_p": 0.9,
+            "num_predict": 500
+        }
+    });
+    ollama_generate(&request_body, Duration::from_secs(30), retry, progress).await
+}
+
+/// Ask Ollama to load the model without generating anything; an empty prompt does just that.
+/// Loading a large model from disk can take a while, hence the long timeout.
+async fn ollama_warm_up(retry: RetryPolicy) -> Result<()> {
+    let request_body = serde_json::json!({
+        "model": OLLAMA_MODEL,
+        "prompt": "",
+        "stream": false
+    });
+    ollama_generate(&request_body, Duration::from_secs(300), retry, None).await.map(|_| ())
+}
+
+/// POST to Ollama's generate endpoint, retrying failures with exponential backoff.
+/// A missing model (404) fails immediately since retrying can't fix it.
+/// Streamed requests (`"stream": true`) must pass `progress` to receive the pieces.
+async fn ollama_generate(
+    request_body: &serde_json::Value,
+    timeout: Duration,
+    retry: RetryPolicy,
+    progress: Option<&mpsc::UnboundedSender<String>>,
+) -> Result<String> {
+    let client = reqwest::Client::new();
+    let mut retry_count = 0;
+    
+    loop {
+        match client
+            .post("http://localhost:11434/api/generate")
+            .json(request_body)
+            .timeout(timeout)
+            .send()
+            .await
+        {
+            Ok(response) => {
+                if response.status().is_success() {
+                    if let Some(progress) = progress {
+                        return read_ollama_stream(response, progress).await;
+                    }
+                    
+                    #[derive(serde::Deserialize)]
+                    struct OllamaResponse {
+                        response: String,
+                    }
+                    
+                    match response.json::<OllamaResponse>().await {
+                        Ok(ollama_response) => {
+                            return Ok(ollama_response.response.trim().to_string());
+                        }
+                        Err(e) => {
+                            tracing::error!("Failed to parse Ollama response: {}", e);
+                            return Err(anyhow::anyhow!("Failed to parse Ollama response: {}", e));
+                        }
+                    }
+                } else {
+                    let status = response.status();
+                    tracing::warn!("Ollama API error: status {}", status);
+                    
+                    // Handle 404 as a specific case for missing model
+                    if status == reqwest::StatusCode::NOT_FOUND {
+                        return Err(anyhow::anyhow!(
+                            "❌ SPAWN FAILED: Missing Ollama model 'gemma3:12b'\n\n\
+                            To use Veda's multi-instance spawning feature, you need to install the gemma3:12b model:\n\
+                            \n\
+                            Run this command in your terminal:\n\
+                            ollama pull gemma3:12b\n\
+                            \n\
+                            This model is used for intelligent task breakdown and coordination between Claude instances.\n\
+                            Without it, spawning additional instances will not work."
+                        ));
+                    }
+                    
+                    if retry_count >= retry.max_retries {
+                        return Err(anyhow::anyhow!("Ollama API error after {} retries: status {}", retry.max_retries, status));
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Failed to contact Ollama (attempt {}/{}): {}", retry_count + 1, retry.max_retries + 1, e);
+                if retry_count >= retry.max_retries {
+                    // Check if it's a connection error
+                    if e.to_string().contains("Connection refused") || e.to_string().contains("error trying to connect") {
+                        return Err(anyhow::anyhow!(
+                            "❌ SPAWN FAILED: Cannot connect to Ollama\n\n\
+                            Ollama is not running. To use Veda's multi-instance spawning feature:\n\
+                            \n\
+                            1. Install Ollama from https://ollama.ai\n\
+                            2. Start Ollama by running: ollama serve\n\
+                            3. Install the required model: ollama pull gemma3:12b\n\
+                            \n\
+                            Without Ollama, spawning additional Veda Slices will not work."
+                        ));
+                    } else {
+                        return Err(anyhow::anyhow!("Failed to contact Ollama after {} retries: {}", retry.max_retries, e));
+                    }
+                }
+            }
+        }
+        
+        retry_count += 1;
+        // Add jitter to prevent thundering herd if multiple processes retry
+        let delay = retry.backoff(retry_count) + Duration::from_millis(rand::thread_rng().gen_range(0..1000));
+        tracing::info!("Retrying Ollama request in {:.1}s (attempt {}/{})...", delay.as_secs_f64(), retry_count, retry.max_retries);
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Collect a streamed generate response, forwarding each piece of the answer to `progress`
+async fn read_ollama_stream(response: reqwest::Response, progress: &mpsc::UnboundedSender<String>) -> Result<String> {
+    use futures_util::StreamExt;
+    
+    let mut stream = response.bytes_stream();
+    let mut buffer = Vec::new();
+    let mut full = String::new();
+    while let Some(bytes) = stream.next().await {
+        let bytes = bytes.map_err(|e| anyhow::anyhow!("Ollama stream interrupted: {}", e))?;
+        buffer.extend_from_slice(&bytes);
+        for piece in drain_stream_lines(&mut buffer) {
+            full.push_str(&piece);
+            let _ = progress.send(piece);
+        }
+    }
+    Ok(full.trim().to_string())
+}
+
+/// Take the complete newline-delimited JSON objects off the front of `buffer` and return
+/// their `response` text, leaving any partial line for the next read
+fn drain_stream_lines(buffer: &mut Vec<u8>) -> Vec<String> {
+    #[derive(serde::Deserialize)]
+    struct OllamaStreamChunk {
+        response: String,
+    }
+    
+    let Some(end) = buffer.iter().rposition(|&b| b == b'\n') else {
+        return Vec::new();
+    };
+    let complete: Vec<u8> = buffer.drain(..=end).collect();
+    String::from_utf8_lossy(&complete)
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str::<OllamaStreamChunk>(line) {
+            Ok(chunk) => Some(chunk.response),
+            Err(e) => {
+                tracing::warn!("Skipping unparseable Ollama stream line: {}", e);
+                None
+            }
+        })
+        .filter(|piece| !piece.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mock_analysis_uses_queued_then_default_responses() {
+        let client = MockAnalysisClient::new("SINGLE_INSTANCE_SUFFICIENT");
+        client.push_response("COORDINATE_BENEFICIAL: three modules");
+
+        assert_eq!(client.analyze("first".to_string()).await.unwrap(), "COORDINATE_BENEFICIAL: three modules");
+        assert_eq!(client.plan("second".to_string()).await.unwrap(), "SINGLE_INSTANCE_SUFFICIENT");
+        assert_eq!(client.prompts(), vec!["first".to_string(), "second".to_string()]);
+    }
+
+    #[test]
+    fn test_drain_stream_lines_keeps_partial_line() {
+        let mut buffer = b"{\"response\":\"SUB\",\"done\":false}\n{\"response\":\"TASK_1\",\"done\":false}\n{\"respo".to_vec();
+        assert_eq!(drain_stream_lines(&mut buffer), vec!["SUB", "TASK_1"]);
+        assert_eq!(buffer, b"{\"respo");
+
+        buffer.extend_from_slice(b"nse\":\"\",\"done\":true}\n");
+        assert!(drain_stream_lines(&mut buffer).is_empty());
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_retry_backoff_doubles_up_to_cap() {
+        let retry = RetryPolicy { max_retries: 8, base_delay: Duration::from_millis(500) };
+        let delays: Vec<u64> = (1..=8).map(|attempt| retry.backoff(attempt).as_millis() as u64).collect();
+        assert_eq!(delays, [500, 1000, 2000, 4000, 8000, 16000, 30000, 30000]);
+        assert_eq!(RetryPolicy::default().max_retries, 5);
+    }
+
+    #[tokio::test]
+    async fn test_mock_claude_starts_session_for_new_requests() {
+        let client = MockClaudeClient::default();
+        let (tx, mut rx) = mpsc::channel(10);
+        let tab = Uuid::new_v4();
+
+        client.send(ClaudeRequest { message: "hi".to_string(), target_tab_id: Some(tab), ..Default::default() }, tx).await.unwrap();
+
+        match rx.recv().await {
+            Some(ClaudeMessage::SessionStarted { session_id, target_tab_id }) => {
+                assert_eq!(session_id, "mock-session-1");
+                assert_eq!(target_tab_id, Some(tab));
+            }
+            other => panic!("expected SessionStarted, got {:?}", other),
+        }
+        assert!(matches!(rx.recv().await, Some(ClaudeMessage::StreamStart { .. })));
+        assert_eq!(client.requests()[0].message, "hi");
+    }
+}
@@ -0,0 +1,109 @@
+/// One `!` command as listed by `!help`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandHelp {
+    pub usage: &'static str,
+    pub summary: &'static str,
+}
+
+/// Every `!` command the input box understands, in the order `!help` shows them
+pub const COMMANDS: &[CommandHelp] = &[
+    CommandHelp { usage: "!help", summary: "Show this help (also F1)" },
+    CommandHelp { usage: "!cd [path|-]", summary: "Change the slice's directory; no path goes to $HOME, - goes back" },
+    CommandHelp { usage: "!max <n>", summary: "Set how many slices may run at once" },
+    CommandHelp { usage: "!duplicate [--with-history]", summary: "Open a copy of this slice on a fresh session" },
+    CommandHelp { usage: "!env [KEY=VALUE]", summary: "Set (or list) environment variables for this slice's Claude" },
+    CommandHelp { usage: "!claude-model [name|default]", summary: "Pick the Claude model for this slice" },
+    CommandHelp { usage: "!scope [text|clear]", summary: "Show or set the scope reminder sent with each message" },
+    CommandHelp { usage: "!theme [name]", summary: "Switch the color theme" },
+    CommandHelp { usage: "!approve / !deny", summary: "Answer a pending tool approval" },
+    CommandHelp { usage: "!tools [list|reset]", summary: "Show or clear this slice's tool permission records" },
+    CommandHelp { usage: "!cancel [id|kind|all]", summary: "List or abort running background analyses" },
+    CommandHelp { usage: "!export json|md [file]", summary: "Save this slice's conversation as JSON or Markdown" },
+    CommandHelp { usage: "!share", summary: "Upload this slice's transcript (secrets redacted) and post the link" },
+    CommandHelp { usage: "!import <file> [--no-session]", summary: "Load a saved conversation into a new slice" },
+    CommandHelp { usage: "!log tail [lines]", summary: "Follow the end of debug.log in a popup (default 200 lines)" },
+    CommandHelp { usage: "!broadcast <slice,slice> <msg>", summary: "Send a message to just the named slices, from any view" },
+    CommandHelp { usage: "!worktree [merge|remove <branch>]", summary: "List, merge or discard the worktrees of closed slices" },
+    CommandHelp { usage: "!after <slice> <msg>", summary: "Hold a message for this slice until the named slice reports done" },
+    CommandHelp { usage: "!recoordinate <task>", summary: "Re-split a task across the open slices and send each its new scope" },
+    CommandHelp { usage: "!owner [path]", summary: "Show which slice last edited a file (or every edited file)" },
+    CommandHelp { usage: "!capabilities", summary: "Remind this slice's Claude session of its Veda and MCP tools" },
+    CommandHelp { usage: "!prompt [n]", summary: "Show the full prompt Claude got for your last (or n-th last) message" },
+    CommandHelp { usage: "!stats [reset]", summary: "Show how often coordination was recommended, spawned slices or failed" },
+    CommandHelp { usage: "!export-tasks", summary: "Write the last coordination breakdown to tasks/tasks.json" },
+];
+
+/// Keybindings as listed by `!help`
+pub const KEYBINDINGS: &[(&str, &str)] = &[
+    ("Enter / Shift+Enter", "Send / new line (swapped by enter_sends = false; Ctrl+Enter sends)"),
+    ("Ctrl+N / Ctrl+X", "New slice / close slice"),
+    ("Ctrl+Shift+D", "Duplicate slice"),
+    ("Ctrl+←/→", "Switch slice"),
+    ("Ctrl+G", "Find a slice by name, directory or state"),
+    ("Ctrl+U", "Jump to the next slice with unread messages"),
+    ("Ctrl+E", "Read the newest truncated message in full"),
+    ("Ctrl+A", "Toggle automode"),
+    ("Ctrl+O", "Stop automode's held answer and write your own"),
+    ("Ctrl+M", "Toggle coordination"),
+    ("Ctrl+T", "Show or hide thinking"),
+    ("Ctrl+P", "Toggle prompt preview"),
+    ("Ctrl+Q", "Message queue"),
+    ("Ctrl+D", "Todo list"),
+    ("Ctrl+Y", "Copy Claude's last reply"),
+    ("Ctrl+F", "Focus mode"),
+    ("Alt+V", "Cycle view: all, claude only, no tools, errors only"),
+    ("Alt+P", "Show the full prompt sent for your last message"),
+    ("Alt+S", "Show or hide the Global view status box"),
+    ("Alt+W", "Toggle line wrapping"),
+    ("Alt+←/→", "Scroll sideways when not wrapping"),
+    ("Ctrl+click", "Open link"),
+    ("Ctrl+C / Esc", "Copy selection / quit (Esc skips a coordination countdown first)"),
+];
+
+/// The `!help` text: commands, keybindings and what the two background modes do
+pub fn help_text() -> String {
+    let width = COMMANDS.iter().map(|c| c.usage.len()).max().unwrap_or(0);
+    let commands: Vec<String> = COMMANDS
+        .iter()
+        .map(|c| format!("  {:width$}  {}", c.usage, c.summary, width = width))
+        .collect();
+    let key_width = KEYBINDINGS.iter().map(|(keys, _)| keys.chars().count()).max().unwrap_or(0);
+    let keys: Vec<String> = KEYBINDINGS
+        .iter()
+        .map(|(keys, action)| format!("  {:width$}  {}", keys, action, width = key_width))
+        .collect();
+
+    format!(
+        "❓ Veda help\n\nCommands:\n{}\n\nKeys:\n{}\n\n\
+        Automode: when Claude asks a question or is denied a tool, the local model answers or decides for you.\n\
+        Coordination: large tasks are broken into subtasks and spread across new slices that work in parallel.",
+        commands.join("\n"),
+        keys.join("\n"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_help_lists_every_command_and_key() {
+        let help = help_text();
+        // An entry's words, whatever the column padding
+        let line = |start: &str| {
+            help.lines()
+                .map(str::trim_start)
+                .find(|line| line.starts_with(start))
+                .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        };
+        assert_eq!(line("!cd").as_deref(), Some("!cd [path|-] Change the slice's directory; no path goes to $HOME, - goes back"));
+        assert_eq!(line("!owner").as_deref(), Some("!owner [path] Show which slice last edited a file (or every edited file)"));
+        assert_eq!(line("Ctrl+E").as_deref(), Some("Ctrl+E Read the newest truncated message in full"));
+        assert_eq!(line("Ctrl+G").as_deref(), Some("Ctrl+G Find a slice by name, directory or state"));
+        // Commands come before keys, each under its heading
+        let commands = help.find("Commands:\n").unwrap();
+        let keys = help.find("Keys:\n").unwrap();
+        assert!(commands < help.find("  !help").unwrap() && help.find("  !export-tasks").unwrap() < keys);
+        assert!(keys < help.find("  Enter / Shift+Enter").unwrap());
+    }
+}
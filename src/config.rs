@@ -0,0 +1,366 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// Environment variable that overrides the config file location
+const CONFIG_PATH_ENV: &str = "VEDA_CONFIG";
+const DEFAULT_THEME: &str = "dark";
+const DEFAULT_BROADCAST_TAG: &str = "[Global broadcast]";
+
+/// How control characters in message content are handled before rendering
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SanitizeMode {
+    /// Replace every control character (except newline and tab) with `?`
+    #[default]
+    Replace,
+    /// Remove ANSI escape sequences and any other control characters
+    Strip,
+    /// Render ANSI SGR colors and styles, dropping all other sequences
+    Ansi,
+}
+
+/// What automode does when Claude is denied a tool
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolApprovalMode {
+    /// Ask Ollama and enable the tool if it's judged safe
+    #[default]
+    Auto,
+    /// Show Ollama's verdict and wait for `!approve` or `!deny`
+    Ask,
+    /// Never enable denied tools
+    Never,
+}
+
+/// What a new slice does with its directory's `.veda/first-message.txt`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FirstMessageMode {
+    /// Put it in the input box to edit or send with Enter
+    #[default]
+    Suggest,
+    /// Send it straight away as the session's first message
+    Send,
+    /// Ignore the file
+    Off,
+}
+
+/// How messages queued while a slice was busy are sent once it's free
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueueDelivery {
+    /// All of them at once, as one "Multiple messages:" prompt
+    #[default]
+    Combine,
+    /// One per reply: the next goes out when Claude has answered the last
+    Sequential,
+    /// As many whole messages as fit in `queue_max_combined_chars`, the rest after the reply
+    Capped,
+}
+
+/// A signal used to stop a slice's Claude process
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum InterruptSignal {
+    /// Like pressing Ctrl+C; Claude stops the current turn and exits
+    #[default]
+    Sigint,
+    /// Ask the process to terminate
+    Sigterm,
+    /// Kill the process outright
+    Sigkill,
+}
+
+impl std::fmt::Display for InterruptSignal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Sigint => "SIGINT",
+            Self::Sigterm => "SIGTERM",
+            Self::Sigkill => "SIGKILL",
+        })
+    }
+}
+
+/// Shell commands run on slice lifecycle events (`[hooks]`); an empty command runs nothing
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HookCommands {
+    /// A slice was spawned by coordination
+    pub spawned: String,
+    /// A slice's Claude session started
+    pub session_started: String,
+    /// A slice reported its subtask done
+    pub done: String,
+    /// A slice was closed
+    pub closed: String,
+    /// Claude reported an error in a slice
+    pub error: String,
+}
+
+/// User configuration for Veda, read from a TOML file.
+/// Every field has a default so a partial (or missing) file is fine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VedaConfig {
+    /// Name of the theme preset to start with (`dark` or `light`)
+    pub theme: String,
+    /// Per-role color overrides applied on top of the preset, e.g. `user = "lightblue"`
+    pub colors: HashMap<String, String>,
+    /// Give coordinated slices a short scope reminder prepended to each message
+    pub scope_preamble: bool,
+    /// Control character handling for message content
+    pub sanitize: SanitizeMode,
+    /// Seconds after a background slice's Claude process died mid-turn (or exited with an
+    /// error) before its session is resumed; 0 disables the keepalive
+    pub keepalive_interval_secs: u64,
+    /// How denied tools are handled in automode
+    pub tool_approval_mode: ToolApprovalMode,
+    /// Also write coordination breakdowns into `tasks/tasks.json` for TaskMaster
+    pub export_breakdown_to_tasks: bool,
+    /// Show the fully assembled prompt in a confirmation popup before sending
+    pub preview_before_send: bool,
+    /// Automatic answers automode may give a slice before waiting for the user; 0 means no limit
+    pub automode_max_exchanges: usize,
+    /// Phrases (case-insensitive) that make automode leave Claude's message for the user to answer
+    pub automode_ask_me_patterns: Vec<String>,
+    /// Interleave the Global view by message time instead of listing slice by slice
+    pub global_view_chronological: bool,
+    /// Start with the slice status overlay shown in the Global view (Alt+S toggles it)
+    pub global_status_overlay: bool,
+    /// Open the URL under the mouse on Ctrl+click; plain clicks still select text
+    pub ctrl_click_opens_links: bool,
+    /// Ask before closing a slice whose Claude process is still working
+    pub confirm_close_busy_slice: bool,
+    /// Times a failed Ollama request is retried before giving up
+    pub ollama_max_retries: u32,
+    /// Wait before the first Ollama retry, doubled for each retry after that
+    pub ollama_retry_base_delay_ms: u64,
+    /// Show the task breakdown as Ollama writes it instead of a static "Analyzing..." message
+    pub stream_analysis_progress: bool,
+    /// Have the analysis model summarize a slice's work into the main slice when it's closed
+    pub summarize_on_close: bool,
+    /// Tag put in front of Global broadcasts, both in each slice's history and in what Claude receives;
+    /// empty sends the message untagged
+    pub broadcast_tag: String,
+    /// Ask before a Global broadcast when more than this many slices would receive it; 0 never asks
+    pub confirm_broadcast_threshold: usize,
+    /// Paste service `!share` uploads transcripts to; empty disables sharing
+    pub share_endpoint: String,
+    /// Extra HTTP headers for the paste service, e.g. `Authorization`
+    pub share_headers: HashMap<String, String>,
+    /// Show key hints and the working directory in a slice with no messages yet
+    pub empty_slice_hint: bool,
+    /// First signal sent when interrupting or closing a slice's Claude process
+    pub interrupt_signal: InterruptSignal,
+    /// Signals sent next, one at a time, while the process keeps running
+    pub interrupt_escalation: Vec<InterruptSignal>,
+    /// How long to wait for the process to exit after each signal before escalating
+    pub interrupt_escalation_delay_ms: u64,
+    /// File whose contents replace the built-in capabilities prompt sent at the start of each
+    /// session, relative to this config file's directory; empty uses the built-in one
+    pub capabilities_file: String,
+    /// Close a spawned slice once it reports its subtask done, after telling the slice that spawned it
+    pub auto_close_done_slices: bool,
+    /// Draw a "── 14:32 ──" line between messages sent at least this many minutes apart; 0 never does
+    pub time_separator_minutes: u64,
+    /// Start DeepSeek chain-of-thought messages collapsed, to be expanded with a click. Unlike
+    /// toggling chain of thought off, each message can still be opened on its own
+    pub collapse_thinking_by_default: bool,
+    /// Load the Ollama model in the background at startup so the first coordination request
+    /// doesn't wait for it (and risk its timeout)
+    pub ollama_warmup: bool,
+    /// Send DeepSeek's automode answer on to Claude; off, it's only shown in the slice
+    pub forward_deepseek_to_claude: bool,
+    /// Draw at most this many lines of any one message, with a Ctrl+E hint to read the rest in
+    /// a popup; 0 draws messages in full
+    pub max_message_lines: usize,
+    /// Give each slice spawned by coordination its own `git worktree` on a new branch, when the
+    /// working directory is inside a git repository, so parallel slices can't overwrite each other
+    pub spawn_in_worktrees: bool,
+    /// Whether a new slice picks up the `.veda/first-message.txt` of its working directory
+    pub first_message: FirstMessageMode,
+    /// Keep the coordination counters shown by `!stats` in `stats.json` next to this file,
+    /// so they add up across restarts
+    pub persist_stats: bool,
+    /// Seconds to hold DeepSeek's automode answer before sending it to Claude, so Ctrl+O can
+    /// stop it and let you answer instead; 0 sends it straight away
+    pub automode_answer_delay_secs: u64,
+    /// Save the last todo list Claude wrote in its slice's directory (`.veda/todos.json`) and
+    /// show it on Ctrl+D after a restart, before Claude lists its todos again
+    pub persist_todos: bool,
+    /// Enter sends and Shift+Enter starts a new line; false swaps them, so Enter starts a new
+    /// line and Ctrl+Enter (or Alt+Enter) sends, and pressing that three times interrupts
+    pub enter_sends: bool,
+    /// Replace likely secrets (API keys, tokens, private keys) with `[REDACTED]` in `!export`
+    /// files and `!share` uploads
+    pub redact_exports: bool,
+    /// Redact the same patterns in debug.log
+    pub redact_logs: bool,
+    /// Redact them on screen too; the messages themselves keep the original text
+    pub redact_display: bool,
+    /// Regexes for secrets to redact on top of the built-in key and token formats
+    pub secret_patterns: Vec<String>,
+    /// Bytes of a streamed DeepSeek answer automode collects for Claude; past this the rest is
+    /// only shown, and Claude gets the start with a note that it was cut off. 0 means no limit
+    pub deepseek_response_max_bytes: usize,
+    /// How the message queue is sent once the slice is free
+    pub queue_delivery: QueueDelivery,
+    /// Characters of queued messages combined into one prompt with `queue_delivery = "capped"`;
+    /// a longer single message still goes out on its own
+    pub queue_max_combined_chars: usize,
+    /// Seconds between automode's Ollama check recommending coordination and the breakdown
+    /// starting, counted down in the slice so Esc or `!cancel coordination` can stop it before
+    /// any slice is spawned; 0 starts at once
+    pub coordination_grace_secs: u64,
+    /// Seconds each `--batch` entry may take, automode follow-ups included, before the batch
+    /// gives up and exits with an error; 0 waits forever
+    pub batch_entry_timeout_secs: u64,
+    /// Commands to run on slice lifecycle events, with the slice's details in `VEDA_*`
+    /// environment variables
+    pub hooks: HookCommands,
+}
+
+impl Default for VedaConfig {
+    fn default() -> Self {
+        Self {
+            theme: DEFAULT_THEME.to_string(),
+            colors: HashMap::new(),
+            scope_preamble: true,
+            sanitize: SanitizeMode::default(),
+            keepalive_interval_secs: 0,
+            tool_approval_mode: ToolApprovalMode::default(),
+            export_breakdown_to_tasks: false,
+            preview_before_send: false,
+            automode_max_exchanges: 10,
+            automode_ask_me_patterns: Vec::new(),
+            global_view_chronological: true,
+            global_status_overlay: true,
+            ctrl_click_opens_links: true,
+            confirm_close_busy_slice: true,
+            ollama_max_retries: 5,
+            ollama_retry_base_delay_ms: 1000,
+            stream_analysis_progress: false,
+            summarize_on_close: false,
+            broadcast_tag: DEFAULT_BROADCAST_TAG.to_string(),
+            confirm_broadcast_threshold: 3,
+            share_endpoint: String::new(),
+            share_headers: HashMap::new(),
+            empty_slice_hint: true,
+            interrupt_signal: InterruptSignal::Sigint,
+            interrupt_escalation: vec![InterruptSignal::Sigkill],
+            interrupt_escalation_delay_ms: 2000,
+            capabilities_file: String::new(),
+            auto_close_done_slices: false,
+            time_separator_minutes: 0,
+            collapse_thinking_by_default: false,
+            ollama_warmup: false,
+            forward_deepseek_to_claude: true,
+            max_message_lines: 0,
+            spawn_in_worktrees: false,
+            first_message: FirstMessageMode::default(),
+            persist_stats: false,
+            automode_answer_delay_secs: 0,
+            persist_todos: false,
+            enter_sends: true,
+            redact_exports: true,
+            redact_logs: true,
+            redact_display: false,
+            secret_patterns: Vec::new(),
+            deepseek_response_max_bytes: 64 * 1024,
+            queue_delivery: QueueDelivery::default(),
+            queue_max_combined_chars: 8000,
+            coordination_grace_secs: 5,
+            batch_entry_timeout_secs: 900,
+            hooks: HookCommands::default(),
+        }
+    }
+}
+
+impl VedaConfig {
+    /// Location of the config file: `$VEDA_CONFIG`, else `~/.config/veda/config.toml`
+    pub fn path() -> PathBuf {
+        if let Ok(path) = std::env::var(CONFIG_PATH_ENV) {
+            return PathBuf::from(path);
+        }
+        let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+        PathBuf::from(home).join(".config").join("veda").join("config.toml")
+    }
+
+    /// Load the config, falling back to defaults if the file is missing or invalid
+    pub fn load() -> Self {
+        let path = Self::path();
+        if !path.exists() {
+            tracing::info!("No config file at {}, using defaults", path.display());
+            return Self::default();
+        }
+        match Self::load_from(&path) {
+            Ok(config) => {
+                tracing::info!("Loaded config from {}", path.display());
+                config
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load config from {}: {}, using defaults", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&contents)?)
+    }
+
+    /// A file named in the config at `config_path`: `~/` is the home directory and a relative
+    /// path sits next to the config, whatever directory Veda happens to be in
+    pub fn resolve_file(config_path: &Path, file: &str) -> PathBuf {
+        if let Some(rest) = file.strip_prefix("~/") {
+            if let Ok(home) = std::env::var("HOME") {
+                return PathBuf::from(home).join(rest);
+            }
+        }
+        config_path.parent().unwrap_or(Path::new("")).join(file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_config_uses_defaults() {
+        let config: VedaConfig = toml::from_str("").unwrap();
+        assert_eq!(config.theme, "dark");
+        assert!(config.colors.is_empty());
+        assert_eq!(config.sanitize, SanitizeMode::Replace);
+        assert_eq!(config.tool_approval_mode, ToolApprovalMode::Auto);
+        assert_eq!(config.first_message, FirstMessageMode::Suggest);
+        let config: VedaConfig = toml::from_str("first_message = \"send\"").unwrap();
+        assert_eq!(config.first_message, FirstMessageMode::Send);
+    }
+
+    #[test]
+    fn test_load_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(
+            &path,
+            "theme = \"light\"\nsanitize = \"ansi\"\ninterrupt_escalation = [\"SIGTERM\", \"SIGKILL\"]\n\n[colors]\nuser = \"red\"\n",
+        ).unwrap();
+
+        let config = VedaConfig::load_from(&path).unwrap();
+        assert_eq!(config.theme, "light");
+        assert_eq!(config.sanitize, SanitizeMode::Ansi);
+        assert_eq!(config.colors.get("user").map(String::as_str), Some("red"));
+        assert_eq!(config.interrupt_signal, InterruptSignal::Sigint);
+        assert_eq!(config.interrupt_escalation, vec![InterruptSignal::Sigterm, InterruptSignal::Sigkill]);
+    }
+
+    #[test]
+    fn test_files_resolve_next_to_the_config() {
+        let config_path = Path::new("/etc/veda/config.toml");
+        assert_eq!(VedaConfig::resolve_file(config_path, "capabilities.md"), Path::new("/etc/veda/capabilities.md"));
+        assert_eq!(VedaConfig::resolve_file(config_path, "/srv/prompt.md"), Path::new("/srv/prompt.md"));
+    }
+}
@@ -94,6 +94,23 @@ pub fn analyze_claude_message(message: &str) -> (bool, Option<String>) {
     }
 }
 
+/// Whether Claude's message ends by handing the turn back to the user: a closing question, or
+/// asking to be told how to go on. A question somewhere in the middle doesn't count.
+pub fn is_awaiting_user_input(message: &str) -> bool {
+    let last_line = message.lines()
+        .rev()
+        .map(|line| line.trim().trim_matches(|c| c == '*' || c == '_' || c == '`').trim())
+        .find(|line| !line.is_empty());
+    let Some(last_line) = last_line else {
+        return false;
+    };
+    let lowercase = last_line.to_lowercase();
+    lowercase.ends_with('?')
+        || ["let me know", "would you like", "do you want", "please confirm", "waiting for your"]
+            .iter()
+            .any(|phrase| lowercase.contains(phrase))
+}
+
 /// Check if Claude is mentioning tool permission issues and get DeepSeek's judgment
 pub async fn check_tool_permission_issue(message: &str, attempted_tools: &[String]) -> Result<Option<Vec<String>>> {
     tracing::info!("Checking for tool permission issues in Claude's message with attempted tools: {:?}", attempted_tools);
@@ -184,12 +201,13 @@ fn is_chain_of_thought(text: &str) -> bool {
 pub async fn generate_deepseek_stall_response(
     claude_message: &str,
     user_context: &str,
+    working_dir: &str,
     tx: mpsc::Sender<DeepSeekMessage>,
 ) -> Result<()> {
     tracing::info!("Generating stall intervention response for Claude's last message: {}", claude_message);
     
     // First, try to get current project status via TaskMaster AI
-    let project_status = get_taskmaster_status().await.unwrap_or_else(|e| {
+    let project_status = get_taskmaster_status(working_dir).await.unwrap_or_else(|e| {
         tracing::warn!("Failed to get TaskMaster status: {}", e);
         "No task information available".to_string()
     });
@@ -342,16 +360,11 @@ MESSAGE_TO_CLAUDE_WITH_VERDICT:"#,
 }
 
 /// Get current project status from TaskMaster AI via direct MCP calls
-async fn get_taskmaster_status() -> Result<String> {
-    // Get current working directory for project root
-    let current_dir = std::env::current_dir()
-        .map(|p| p.display().to_string())
-        .unwrap_or_else(|_| ".".to_string());
-    
+async fn get_taskmaster_status(current_dir: &str) -> Result<String> {
     tracing::info!("Getting TaskMaster status via MCP for project: {}", current_dir);
     
     // First check if TaskMaster is initialized
-    match ensure_taskmaster_initialized(&current_dir).await {
+    match ensure_taskmaster_initialized(current_dir).await {
         Ok(init_message) => {
             if !init_message.is_empty() {
                 return Ok(format!("TaskMaster Initialization: {}", init_message));
@@ -521,10 +534,9 @@ async fn call_taskmaster_mcp(method: &str, params: &serde_json::Value) -> Result
     use tokio::process::Command;
     use std::process::Stdio;
     
-    // Get current working directory for project root
-    let current_dir = std::env::current_dir()
-        .map(|p| p.display().to_string())
-        .unwrap_or_else(|_| ".".to_string());
+    // Run in the project root the caller passed rather than the process cwd, which follows
+    // whichever tab is selected and can change while this is in flight
+    let current_dir = params.get("projectRoot").and_then(|root| root.as_str()).unwrap_or(".").to_string();
     
     tracing::debug!("Calling TaskMaster AI via npx: {} with params: {}", method, params);
     
@@ -876,6 +888,14 @@ mod tests {
         assert!(!is_question);
     }
     
+    #[test]
+    fn test_awaiting_user_input_needs_a_closing_question() {
+        assert!(is_awaiting_user_input("I found two options.\n\n**Which approach do you prefer?**\n"));
+        assert!(is_awaiting_user_input("The migration is ready. Let me know if I should run it."));
+        assert!(!is_awaiting_user_input("Why did it fail? The path was wrong, so I fixed it.\nAll tests pass."));
+        assert!(!is_awaiting_user_input(""));
+    }
+    
     #[test]
     fn test_analyze_documentation_hints() {
         let (_, hint) = analyze_claude_message("How do I use the React documentation?");
@@ -0,0 +1,37 @@
+/// How well `query` matches `candidate` as a case-insensitive subsequence, or `None` if it
+/// doesn't. Higher is better: consecutive characters and matches at word starts score more.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<u32> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match: Option<usize> = None;
+
+    for wanted in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = candidate[position..].iter().position(|&c| c == wanted)? + position;
+        score += 1;
+        if previous_match.is_some_and(|previous| previous + 1 == found) {
+            score += 4;
+        }
+        if found == 0 || !candidate[found - 1].is_alphanumeric() {
+            score += 2;
+        }
+        previous_match = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score_prefers_tight_matches() {
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+        assert_eq!(fuzzy_score("xyz", "Slice 1"), None);
+        let tight = fuzzy_score("api", "Slice 3 ~/src/api-server").unwrap();
+        let loose = fuzzy_score("api", "Slice 2 ~/apps/pipeline").unwrap();
+        assert!(tight > loose);
+        assert!(fuzzy_score("S3", "Slice 3").is_some());
+    }
+}
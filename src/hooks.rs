@@ -0,0 +1,140 @@
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+use crate::config::HookCommands;
+
+/// How long a hook may run before it's killed
+const HOOK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A point in a slice's life that can run a `[hooks]` command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    Spawned,
+    SessionStarted,
+    Done,
+    Closed,
+    Error,
+}
+
+impl HookEvent {
+    /// Name passed to the hook as `VEDA_EVENT`, the same as its `[hooks]` key
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Spawned => "spawned",
+            Self::SessionStarted => "session_started",
+            Self::Done => "done",
+            Self::Closed => "closed",
+            Self::Error => "error",
+        }
+    }
+
+    fn command(self, hooks: &HookCommands) -> &str {
+        match self {
+            Self::Spawned => &hooks.spawned,
+            Self::SessionStarted => &hooks.session_started,
+            Self::Done => &hooks.done,
+            Self::Closed => &hooks.closed,
+            Self::Error => &hooks.error,
+        }
+    }
+}
+
+/// The slice an event happened to, as handed to the hook
+#[derive(Debug, Clone, Default)]
+pub struct HookContext {
+    pub slice: String,
+    pub slice_id: String,
+    pub session_id: Option<String>,
+    pub working_dir: String,
+    /// The error text, the spawned slice's subtask, and so on; empty when there's nothing to add
+    pub detail: String,
+}
+
+impl HookContext {
+    fn env(&self, event: HookEvent) -> Vec<(&'static str, String)> {
+        vec![
+            ("VEDA_EVENT", event.name().to_string()),
+            ("VEDA_SLICE", self.slice.clone()),
+            ("VEDA_SLICE_ID", self.slice_id.clone()),
+            ("VEDA_SESSION_ID", self.session_id.clone().unwrap_or_default()),
+            ("VEDA_WORKING_DIR", self.working_dir.clone()),
+            ("VEDA_DETAIL", self.detail.clone()),
+        ]
+    }
+}
+
+/// Start the command configured for `event`, if any, in the background. It runs through the
+/// shell in the slice's directory; failures are only logged, since nobody is waiting on it.
+pub fn run(hooks: &HookCommands, event: HookEvent, context: HookContext) -> Option<tokio::task::JoinHandle<()>> {
+    let command = event.command(hooks).trim();
+    if command.is_empty() {
+        return None;
+    }
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    };
+    cmd.envs(context.env(event))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if std::path::Path::new(&context.working_dir).is_dir() {
+        cmd.current_dir(&context.working_dir);
+    }
+    tracing::info!("Running {} hook for {}", event.name(), context.slice);
+
+    Some(tokio::spawn(async move {
+        let child = match cmd.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                tracing::warn!("Couldn't start the {} hook: {}", event.name(), e);
+                return;
+            }
+        };
+        match tokio::time::timeout(HOOK_TIMEOUT, child.wait_with_output()).await {
+            Ok(Ok(output)) if output.status.success() => {}
+            Ok(Ok(output)) => tracing::warn!(
+                "{} hook exited with {}: {}",
+                event.name(),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            Ok(Err(e)) => tracing::warn!("{} hook failed: {}", event.name(), e),
+            Err(_) => tracing::warn!("{} hook was killed after {}s", event.name(), HOOK_TIMEOUT.as_secs()),
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_hook_gets_event_context() {
+        let dir = tempfile::tempdir().unwrap();
+        let hooks = HookCommands {
+            done: "echo \"$VEDA_EVENT $VEDA_SLICE $VEDA_SESSION_ID $VEDA_DETAIL\" > hook.txt".to_string(),
+            ..HookCommands::default()
+        };
+        let context = HookContext {
+            slice: "Slice 2".to_string(),
+            session_id: Some("abc".to_string()),
+            working_dir: dir.path().to_string_lossy().to_string(),
+            detail: "Parser".to_string(),
+            ..HookContext::default()
+        };
+
+        assert!(run(&hooks, HookEvent::Closed, context.clone()).is_none());
+        run(&hooks, HookEvent::Done, context).unwrap().await.unwrap();
+        let written = std::fs::read_to_string(dir.path().join("hook.txt")).unwrap();
+        assert_eq!(written.trim(), "done Slice 2 abc Parser");
+    }
+}
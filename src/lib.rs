@@ -1,6 +1,25 @@
+pub mod analysis_tasks;
+pub mod ansi;
+pub mod automode;
+pub mod breakdown;
 pub mod claude;
+pub mod clients;
+pub mod commands;
+pub mod config;
 pub mod deepseek;
+pub mod fuzzy;
+pub mod hooks;
+pub mod links;
+pub mod log_tail;
+pub mod redact;
+pub mod resources;
+pub mod share;
 pub mod shared_ipc;
+pub mod stats;
+pub mod taskmaster;
+pub mod theme;
+pub mod todo;
+pub mod worktree;
 
 use chrono::Local;
 use uuid::Uuid;
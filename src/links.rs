@@ -0,0 +1,121 @@
+use anyhow::Result;
+use ratatui::style::Style;
+use ratatui::text::Span;
+use std::ops::Range;
+use std::process::{Command, Stdio};
+
+const URL_SCHEMES: &[&str] = &["https://", "http://"];
+// Punctuation that usually ends the sentence around a URL rather than the URL itself
+const TRAILING_PUNCTUATION: &[char] = &['.', ',', ';', ':', '!', '?', '\'', '"', ')', ']', '}', '>'];
+
+/// Byte ranges of the http(s) URLs in `text`
+pub fn find_urls(text: &str) -> Vec<Range<usize>> {
+    let mut urls = Vec::new();
+    let mut search_from = 0;
+
+    while let Some((start, scheme)) = URL_SCHEMES
+        .iter()
+        .filter_map(|scheme| text[search_from..].find(scheme).map(|idx| (search_from + idx, scheme)))
+        .min_by_key(|(idx, _)| *idx)
+    {
+        let rest = &text[start..];
+        let mut end = start + rest.find(|c: char| c.is_whitespace() || c.is_control()).unwrap_or(rest.len());
+        // Drop trailing punctuation, keeping a closing paren that has a partner inside the URL
+        while let Some(last) = text[start..end].chars().last() {
+            let balanced_paren = last == ')' && text[start..end].matches('(').count() >= text[start..end].matches(')').count();
+            if !TRAILING_PUNCTUATION.contains(&last) || balanced_paren {
+                break;
+            }
+            end -= last.len_utf8();
+        }
+        if end > start + scheme.len() {
+            urls.push(start..end);
+        }
+        search_from = end.max(start + scheme.len());
+    }
+    urls
+}
+
+/// The URL covering byte offset `offset` of `text`, if any
+pub fn url_at(text: &str, offset: usize) -> Option<&str> {
+    find_urls(text)
+        .into_iter()
+        .find(|range| range.contains(&offset))
+        .map(|range| &text[range])
+}
+
+/// Split `text` into spans, giving URLs `link_style` and everything else `base`
+pub fn link_spans(text: &str, base: Style, link_style: Style) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut last = 0;
+    for range in find_urls(text) {
+        if range.start > last {
+            spans.push(Span::styled(text[last..range.start].to_string(), base));
+        }
+        spans.push(Span::styled(text[range.clone()].to_string(), link_style));
+        last = range.end;
+    }
+    if last < text.len() || spans.is_empty() {
+        spans.push(Span::styled(text[last..].to_string(), base));
+    }
+    spans
+}
+
+/// Open `url` with the platform's default handler without waiting for it
+pub fn open_url(url: &str) -> Result<()> {
+    let mut cmd = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", "start", ""]);
+        cmd
+    } else {
+        Command::new("xdg-open")
+    };
+    cmd.arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::style::Modifier;
+
+    fn urls(text: &str) -> Vec<&str> {
+        find_urls(text).into_iter().map(|range| &text[range]).collect()
+    }
+
+    #[test]
+    fn test_find_urls_trims_sentence_punctuation() {
+        assert_eq!(
+            urls("See https://docs.rs/ratatui. Also (http://example.com/a_(b)) and https://x.io/?q=1, done"),
+            ["https://docs.rs/ratatui", "http://example.com/a_(b)", "https://x.io/?q=1"]
+        );
+    }
+
+    #[test]
+    fn test_bare_scheme_is_not_a_url() {
+        assert!(urls("type https:// then the host").is_empty());
+    }
+
+    #[test]
+    fn test_url_at_offset() {
+        let text = "open https://example.com now";
+        assert_eq!(url_at(text, 10), Some("https://example.com"));
+        assert_eq!(url_at(text, 2), None);
+    }
+
+    #[test]
+    fn test_link_spans_split_around_urls() {
+        let link = Style::default().add_modifier(Modifier::UNDERLINED);
+        let spans = link_spans("a https://b.c d", Style::default(), link);
+        assert_eq!(spans.len(), 3);
+        assert_eq!(spans[1].content, "https://b.c");
+        assert_eq!(spans[1].style, link);
+        assert_eq!(link_spans("", Style::default(), link).len(), 1);
+    }
+}
@@ -0,0 +1,46 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+// Only this much of the end of the file is read, however large the log has grown
+const MAX_TAIL_BYTES: u64 = 256 * 1024;
+
+/// The last `max_lines` lines of the file at `path`, reading no more than its final 256 KiB
+pub fn read_tail(path: &Path, max_lines: usize) -> std::io::Result<Vec<String>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(MAX_TAIL_BYTES);
+    file.seek(SeekFrom::Start(start))?;
+    let mut bytes = Vec::with_capacity((len - start) as usize);
+    file.read_to_end(&mut bytes)?;
+
+    let text = String::from_utf8_lossy(&bytes);
+    let mut lines: Vec<&str> = text.lines().collect();
+    // The first line is probably cut in half when reading from the middle of the file
+    if start > 0 && !lines.is_empty() {
+        lines.remove(0);
+    }
+    let skip = lines.len().saturating_sub(max_lines);
+    Ok(lines[skip..].iter().map(|line| line.to_string()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_read_tail_keeps_last_lines_of_large_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        for i in 0..50_000 {
+            writeln!(file, "line {}", i).unwrap();
+        }
+        file.flush().unwrap();
+
+        let tail = read_tail(file.path(), 3).unwrap();
+        assert_eq!(tail, vec!["line 49997", "line 49998", "line 49999"]);
+
+        let short = tempfile::NamedTempFile::new().unwrap();
+        assert!(read_tail(short.path(), 3).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,73 @@
+use sysinfo::{Pid, System};
+
+/// CPU and memory use of one process as of its last sample
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProcessUsage {
+    pub cpu_percent: f32,
+    pub rss_bytes: u64,
+}
+
+impl ProcessUsage {
+    /// e.g. "12% CPU, 340 MB", for tool replies
+    pub fn summary(&self) -> String {
+        format!("{:.0}% CPU, {} MB", self.cpu_percent, self.rss_bytes / (1024 * 1024))
+    }
+
+    /// e.g. "12% 340M", for the narrow status pane
+    pub fn compact(&self) -> String {
+        format!("{:.0}% {}M", self.cpu_percent, self.rss_bytes / (1024 * 1024))
+    }
+}
+
+/// Samples processes by PID. CPU use is measured between two samples of the same process,
+/// so it reads 0% the first time a process is seen.
+pub struct UsageSampler {
+    system: System,
+}
+
+impl UsageSampler {
+    pub fn new() -> Self {
+        Self { system: System::new() }
+    }
+
+    /// Current usage of `pid`, or `None` once it has exited or on platforms sysinfo can't read
+    pub fn sample(&mut self, pid: u32) -> Option<ProcessUsage> {
+        if !sysinfo::IS_SUPPORTED_SYSTEM {
+            return None;
+        }
+        let pid = Pid::from_u32(pid);
+        if !self.system.refresh_process(pid) {
+            return None;
+        }
+        let process = self.system.process(pid)?;
+        Some(ProcessUsage {
+            cpu_percent: process.cpu_usage(),
+            rss_bytes: process.memory(),
+        })
+    }
+}
+
+impl Default for UsageSampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples_own_process_and_formats_usage() {
+        let mut sampler = UsageSampler::new();
+        if sysinfo::IS_SUPPORTED_SYSTEM {
+            let usage = sampler.sample(std::process::id()).unwrap();
+            assert!(usage.rss_bytes > 0);
+        }
+        assert!(sampler.sample(u32::MAX).is_none());
+
+        let usage = ProcessUsage { cpu_percent: 12.4, rss_bytes: 340 * 1024 * 1024 };
+        assert_eq!(usage.summary(), "12% CPU, 340 MB");
+        assert_eq!(usage.compact(), "12% 340M");
+    }
+}
@@ -0,0 +1,61 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::time::Duration;
+
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+// JSON fields paste services commonly put the link in
+const URL_FIELDS: &[&str] = &["url", "html_url", "link", "raw_url"];
+
+/// POST `body` as Markdown to the paste service at `endpoint` and return the link it answers with
+pub async fn upload(endpoint: &str, headers: &HashMap<String, String>, body: String) -> Result<String> {
+    let mut request = reqwest::Client::new()
+        .post(endpoint)
+        .header(reqwest::header::CONTENT_TYPE, "text/markdown; charset=utf-8")
+        .timeout(UPLOAD_TIMEOUT)
+        .body(body);
+    for (name, value) in headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+
+    let response = request.send().await?;
+    let status = response.status();
+    let location = response
+        .headers()
+        .get(reqwest::header::LOCATION)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let text = response.text().await?;
+    if !status.is_success() {
+        return Err(anyhow::anyhow!("{} returned {}: {}", endpoint, status, text.trim()));
+    }
+    extract_url(&text, location.as_deref())
+        .ok_or_else(|| anyhow::anyhow!("{} didn't answer with a link", endpoint))
+}
+
+/// The shared link from a paste service's response: a JSON `url`-like field, a bare URL
+/// body, or the `Location` header
+pub fn extract_url(body: &str, location: Option<&str>) -> Option<String> {
+    if let Ok(json) = serde_json::from_str::<serde_json::Value>(body) {
+        if let Some(url) = URL_FIELDS.iter().find_map(|field| json.get(field).and_then(|v| v.as_str())) {
+            return Some(url.to_string());
+        }
+    }
+    let first_line = body.trim().lines().next().unwrap_or_default().trim();
+    if first_line.starts_with("http://") || first_line.starts_with("https://") {
+        return Some(first_line.to_string());
+    }
+    location.map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_url_from_common_responses() {
+        assert_eq!(extract_url(r#"{"id":"x1","html_url":"https://paste.example/x1"}"#, None).as_deref(), Some("https://paste.example/x1"));
+        assert_eq!(extract_url("https://paste.example/abc\n", None).as_deref(), Some("https://paste.example/abc"));
+        assert_eq!(extract_url("", Some("https://paste.example/loc")).as_deref(), Some("https://paste.example/loc"));
+        assert_eq!(extract_url("created", None), None);
+    }
+}
@@ -485,6 +485,34 @@ impl RegistryClient {
         }
     }
     
+    /// Drop the instance count kept for a session
+    pub async fn clear_session(session_id: &str) -> Result<()> {
+        let cmd = RegistryCommand {
+            command: "clear".to_string(),
+            session_id: session_id.to_string(),
+            value: None,
+        };
+        
+        let response = Self::send_command(cmd).await?;
+        if response.success {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("Failed to clear session: {}", response.message))
+        }
+    }
+    
+    /// Remove registry entries whose Veda process no longer exists, e.g. after a crash.
+    /// Returns the session IDs that were cleaned up.
+    pub async fn clean_stale_sessions() -> Result<Vec<String>> {
+        let session_pids = Self::list_all_session_pids().await?;
+        let stale = stale_sessions(&session_pids, pid_is_alive);
+        for session_id in &stale {
+            Self::unregister_session_pid(session_id).await?;
+            Self::clear_session(session_id).await?;
+        }
+        Ok(stale)
+    }
+    
     /// Get all sessionID -> PID mappings from shared registry
     pub async fn list_all_session_pids() -> Result<HashMap<String, u32>> {
         let cmd = RegistryCommand {
@@ -501,6 +529,30 @@ impl RegistryClient {
     }
 }
 
+/// Whether a process with this PID is still running
+pub fn pid_is_alive(pid: u32) -> bool {
+    use nix::errno::Errno;
+    use nix::sys::signal;
+    use nix::unistd::Pid;
+
+    if pid == 0 {
+        return false;
+    }
+    // Signal 0 only checks for existence; EPERM means it exists but belongs to someone else
+    matches!(signal::kill(Pid::from_raw(pid as i32), None), Ok(()) | Err(Errno::EPERM))
+}
+
+/// Sessions whose registered Veda PID is no longer alive, sorted for stable output
+pub fn stale_sessions(session_pids: &HashMap<String, u32>, is_alive: impl Fn(u32) -> bool) -> Vec<String> {
+    let mut stale: Vec<String> = session_pids
+        .iter()
+        .filter(|(_, pid)| !is_alive(**pid))
+        .map(|(session_id, _)| session_id.clone())
+        .collect();
+    stale.sort();
+    stale
+}
+
 /// Connect to the shared registry as a client to listen for routed messages
 pub async fn connect_to_registry_as_client(
     app_tx: tokio::sync::mpsc::Sender<crate::claude::ClaudeMessage>,
@@ -572,3 +624,28 @@ pub async fn connect_to_registry_as_client(
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_sessions_are_those_with_dead_pids() {
+        let session_pids = HashMap::from([
+            ("alive".to_string(), 100),
+            ("crashed-b".to_string(), 200),
+            ("crashed-a".to_string(), 300),
+        ]);
+        assert_eq!(stale_sessions(&session_pids, |pid| pid == 100), ["crashed-a", "crashed-b"]);
+    }
+
+    #[test]
+    fn test_pid_is_alive() {
+        assert!(pid_is_alive(std::process::id()));
+        assert!(!pid_is_alive(0));
+
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        assert!(!pid_is_alive(pid));
+    }
+}
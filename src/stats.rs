@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// How coordination has fared: whether the Ollama check recommended it, how long that took,
+/// and what became of the breakdowns it led to
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CoordinationStats {
+    /// Times `analyze_task_for_coordination` decided, by keyword or by asking Ollama
+    pub checks: u64,
+    pub recommended: u64,
+    pub declined: u64,
+    pub check_failures: u64,
+    pub check_timeouts: u64,
+    /// Time spent waiting for Ollama's verdict, over all checks
+    pub check_millis: u64,
+    /// Breakdowns that spawned at least one slice, and how many slices they spawned
+    pub spawns: u64,
+    pub slices_spawned: u64,
+    /// Breakdowns Ollama returned without any usable subtask
+    pub empty_breakdowns: u64,
+    pub breakdown_failures: u64,
+    pub breakdown_timeouts: u64,
+    /// Spawned slices that reported their subtask done
+    pub subtasks_done: u64,
+}
+
+impl CoordinationStats {
+    /// Multi-line report for `!stats`
+    pub fn summary(&self) -> String {
+        let average = match self.checks {
+            0 => "-".to_string(),
+            checks => format!("{:.1}s", self.check_millis as f64 / checks as f64 / 1000.0),
+        };
+        format!(
+            "Coordination checks: {} ({} recommended, {} declined, {} failed, {} timed out), {} average wait\n\
+             Breakdowns: {} spawned {} slice(s), {} had no subtasks, {} failed, {} timed out\n\
+             Spawned subtasks reported done: {}",
+            self.checks, self.recommended, self.declined, self.check_failures, self.check_timeouts, average,
+            self.spawns, self.slices_spawned, self.empty_breakdowns, self.breakdown_failures, self.breakdown_timeouts,
+            self.subtasks_done
+        )
+    }
+}
+
+/// Shared counters, updated from the app and from background analysis tasks. With a file,
+/// they start from its contents and every update is written back to it.
+#[derive(Debug, Default)]
+pub struct StatsRecorder {
+    stats: Mutex<CoordinationStats>,
+    file: Option<PathBuf>,
+}
+
+impl StatsRecorder {
+    pub fn new(file: Option<PathBuf>) -> Self {
+        let stats = file
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|contents| match serde_json::from_str(&contents) {
+                Ok(stats) => Some(stats),
+                Err(e) => {
+                    tracing::warn!("Ignoring unreadable coordination stats: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+        Self { stats: Mutex::new(stats), file }
+    }
+
+    pub fn record(&self, update: impl FnOnce(&mut CoordinationStats)) {
+        let Ok(mut stats) = self.stats.lock() else {
+            return;
+        };
+        update(&mut stats);
+        self.save(&stats);
+    }
+
+    pub fn snapshot(&self) -> CoordinationStats {
+        self.stats.lock().map(|stats| stats.clone()).unwrap_or_default()
+    }
+
+    pub fn file(&self) -> Option<&PathBuf> {
+        self.file.as_ref()
+    }
+
+    fn save(&self, stats: &CoordinationStats) {
+        let Some(path) = &self.file else {
+            return;
+        };
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| std::fs::write(path, serde_json::to_string_pretty(stats).unwrap_or_default()));
+        if let Err(e) = result {
+            tracing::warn!("Couldn't save coordination stats to {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_persisted_stats_survive_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("veda").join("stats.json");
+
+        let recorder = StatsRecorder::new(Some(path.clone()));
+        recorder.record(|stats| {
+            stats.checks += 2;
+            stats.recommended += 1;
+            stats.declined += 1;
+            stats.check_millis += 3000;
+        });
+        recorder.record(|stats| stats.empty_breakdowns += 1);
+
+        let restarted = StatsRecorder::new(Some(path));
+        assert_eq!(restarted.snapshot(), recorder.snapshot());
+        let summary = restarted.snapshot().summary();
+        assert!(summary.starts_with("Coordination checks: 2 (1 recommended, 1 declined, 0 failed, 0 timed out), 1.5s average wait"));
+        assert!(summary.contains("1 had no subtasks"));
+
+        let in_memory = StatsRecorder::new(None);
+        in_memory.record(|stats| stats.spawns += 1);
+        assert_eq!(in_memory.snapshot().spawns, 1);
+    }
+}
@@ -0,0 +1,127 @@
+use crate::breakdown::parse_subtasks;
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// One `SUBTASK_*` line from a coordination breakdown, in TaskMaster terms
+#[derive(Debug, Clone, PartialEq)]
+pub struct BreakdownTask {
+    pub title: String,
+    pub scope: Option<String>,
+    pub priority: String,
+}
+
+/// Parse the subtasks of a breakdown into tasks
+pub fn parse_breakdown(breakdown: &str) -> Vec<BreakdownTask> {
+    parse_subtasks(breakdown)
+        .into_iter()
+        .map(|subtask| BreakdownTask {
+            title: subtask.description.to_string(),
+            scope: subtask.scope.map(str::to_string),
+            priority: subtask.priority.map_or_else(|| "medium".to_string(), str::to_lowercase),
+        })
+        .collect()
+}
+
+/// Merge breakdown tasks into a TaskMaster `tasks.json`, creating it if needed.
+/// Tasks whose title is already present are skipped; returns how many were added.
+pub fn merge_into_tasks_file(path: &Path, tasks: &[BreakdownTask]) -> Result<usize> {
+    let mut root = if path.exists() {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("reading {}", path.display()))?;
+        serde_json::from_str::<Value>(&contents)
+            .with_context(|| format!("parsing {}", path.display()))?
+    } else {
+        json!({ "tasks": [] })
+    };
+
+    let existing = root
+        .as_object_mut()
+        .context("tasks file is not a JSON object")?
+        .entry("tasks")
+        .or_insert_with(|| json!([]))
+        .as_array_mut()
+        .context("\"tasks\" is not an array")?;
+
+    let mut next_id = existing
+        .iter()
+        .filter_map(|task| task.get("id").and_then(|id| id.as_u64()))
+        .max()
+        .unwrap_or(0)
+        + 1;
+
+    let mut added = 0;
+    for task in tasks {
+        let already_tracked = existing
+            .iter()
+            .any(|t| t.get("title").and_then(|title| title.as_str()) == Some(task.title.as_str()));
+        if already_tracked {
+            continue;
+        }
+        existing.push(json!({
+            "id": next_id,
+            "title": task.title,
+            "description": task.scope.clone().unwrap_or_default(),
+            "status": "pending",
+            "dependencies": [],
+            "priority": task.priority,
+            "details": "Created from a Veda coordination breakdown",
+        }));
+        next_id += 1;
+        added += 1;
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(&root)?)?;
+    Ok(added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BREAKDOWN: &str = "Here is the plan:\nSUBTASK_1: Build the parser | SCOPE: src/parser.rs | PRIORITY: High\nSUBTASK_2: Write docs | SCOPE: docs/\nnot a subtask";
+
+    #[test]
+    fn test_parse_breakdown() {
+        let tasks = parse_breakdown(BREAKDOWN);
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].title, "Build the parser");
+        assert_eq!(tasks[0].scope.as_deref(), Some("src/parser.rs"));
+        assert_eq!(tasks[0].priority, "high");
+        assert_eq!(tasks[1].priority, "medium");
+    }
+
+    #[test]
+    fn test_merge_creates_file_and_skips_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tasks").join("tasks.json");
+        let tasks = parse_breakdown(BREAKDOWN);
+
+        assert_eq!(merge_into_tasks_file(&path, &tasks).unwrap(), 2);
+        assert_eq!(merge_into_tasks_file(&path, &tasks).unwrap(), 0);
+
+        let root: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let stored = root["tasks"].as_array().unwrap();
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[1]["id"], 2);
+        assert_eq!(stored[1]["status"], "pending");
+    }
+
+    #[test]
+    fn test_merge_preserves_existing_tasks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tasks.json");
+        std::fs::write(&path, r#"{"tasks":[{"id":7,"title":"Existing","status":"done"}],"meta":{"v":1}}"#).unwrap();
+
+        let added = merge_into_tasks_file(&path, &parse_breakdown(BREAKDOWN)).unwrap();
+        assert_eq!(added, 2);
+
+        let root: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(root["meta"]["v"], 1);
+        assert_eq!(root["tasks"][0]["title"], "Existing");
+        assert_eq!(root["tasks"][1]["id"], 8);
+    }
+}
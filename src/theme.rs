@@ -0,0 +1,235 @@
+use ratatui::style::{Color, Modifier, Style};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::config::VedaConfig;
+
+pub const THEME_NAMES: &[&str] = &["dark", "light"];
+
+/// Colors for each semantic role in the UI. The terminal's own background is
+/// left alone; only foregrounds and a few overlay/status backgrounds are themed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    pub text: Color,
+    pub user: Color,
+    pub claude: Color,
+    pub system: Color,
+    pub error: Color,
+    pub tool: Color,
+    pub analyzer: Color,
+    pub thinking: Color,
+    pub timestamp: Color,
+    pub slice_label: Color,
+    pub tab_active: Color,
+    pub tab_inactive: Color,
+    pub selection: Color,
+    pub accent: Color,
+    pub status_bar_fg: Color,
+    pub status_bar_bg: Color,
+    pub overlay_bg: Color,
+    /// Colors handed out to slices in tab order, shared by a slice's tab and its
+    /// `[Slice N]` prefix in the Global view
+    pub slice_palette: &'static [Color],
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Self {
+            name: "dark".to_string(),
+            text: Color::White,
+            user: Color::Cyan,
+            claude: Color::Green,
+            system: Color::Blue,
+            error: Color::Red,
+            tool: Color::Magenta,
+            analyzer: Color::Yellow,
+            thinking: Color::DarkGray,
+            timestamp: Color::DarkGray,
+            slice_label: Color::Magenta,
+            tab_active: Color::Yellow,
+            tab_inactive: Color::White,
+            selection: Color::DarkGray,
+            accent: Color::Yellow,
+            status_bar_fg: Color::White,
+            status_bar_bg: Color::DarkGray,
+            overlay_bg: Color::Black,
+            slice_palette: &[
+                Color::Magenta,
+                Color::Cyan,
+                Color::Green,
+                Color::LightBlue,
+                Color::LightRed,
+                Color::LightYellow,
+            ],
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            name: "light".to_string(),
+            text: Color::Black,
+            user: Color::Blue,
+            claude: Color::Green,
+            system: Color::Magenta,
+            error: Color::Red,
+            tool: Color::Magenta,
+            analyzer: Color::Rgb(176, 112, 0),
+            thinking: Color::Gray,
+            timestamp: Color::Gray,
+            slice_label: Color::Blue,
+            tab_active: Color::Blue,
+            tab_inactive: Color::Black,
+            selection: Color::Gray,
+            accent: Color::Blue,
+            status_bar_fg: Color::Black,
+            status_bar_bg: Color::Gray,
+            overlay_bg: Color::White,
+            slice_palette: &[
+                Color::Magenta,
+                Color::Blue,
+                Color::Green,
+                Color::Rgb(176, 112, 0),
+                Color::Cyan,
+                Color::Red,
+            ],
+        }
+    }
+
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            _ => None,
+        }
+    }
+
+    /// Build the theme described by the config: the named preset plus any color overrides
+    pub fn from_config(config: &VedaConfig) -> Self {
+        let mut theme = Self::by_name(&config.theme).unwrap_or_else(|| {
+            tracing::warn!("Unknown theme '{}' in config, falling back to dark", config.theme);
+            Self::dark()
+        });
+        theme.apply_overrides(&config.colors);
+        theme
+    }
+
+    /// Apply `role = "color"` overrides; unknown roles and unparsable colors are logged and skipped
+    pub fn apply_overrides(&mut self, overrides: &HashMap<String, String>) {
+        for (role, value) in overrides {
+            let color = match Color::from_str(value) {
+                Ok(color) => color,
+                Err(_) => {
+                    tracing::warn!("Invalid color '{}' for theme role '{}'", value, role);
+                    continue;
+                }
+            };
+            match self.role_mut(role) {
+                Some(slot) => *slot = color,
+                None => tracing::warn!("Unknown theme role '{}'", role),
+            }
+        }
+    }
+
+    fn role_mut(&mut self, role: &str) -> Option<&mut Color> {
+        Some(match role {
+            "text" => &mut self.text,
+            "user" => &mut self.user,
+            "claude" => &mut self.claude,
+            "system" => &mut self.system,
+            "error" => &mut self.error,
+            "tool" => &mut self.tool,
+            "analyzer" => &mut self.analyzer,
+            "thinking" => &mut self.thinking,
+            "timestamp" => &mut self.timestamp,
+            "slice_label" => &mut self.slice_label,
+            "tab_active" => &mut self.tab_active,
+            "tab_inactive" => &mut self.tab_inactive,
+            "selection" => &mut self.selection,
+            "accent" => &mut self.accent,
+            "status_bar_fg" => &mut self.status_bar_fg,
+            "status_bar_bg" => &mut self.status_bar_bg,
+            "overlay_bg" => &mut self.overlay_bg,
+            _ => return None,
+        })
+    }
+
+    /// Style for the sender name column of a message
+    pub fn sender_style(&self, sender: &str) -> Style {
+        let color = match sender {
+            "You" => self.user,
+            "Tool" => self.tool,
+            "System" => self.system,
+            "Error" | "DeepSeekError" => self.error,
+            "DeepSeek" | "Ollama" => self.analyzer,
+            "Inbox" => self.slice_label,
+            _ => self.claude,
+        };
+        Style::default().fg(color).add_modifier(Modifier::BOLD)
+    }
+
+    /// Color of the slice at `index`; the palette repeats once there are more slices than colors
+    pub fn slice_color(&self, index: usize) -> Color {
+        self.slice_palette[index % self.slice_palette.len()]
+    }
+
+    pub fn thinking_style(&self) -> Style {
+        Style::default().fg(self.thinking).add_modifier(Modifier::ITALIC)
+    }
+
+    /// Style for URLs inside message text
+    pub fn link_style(&self) -> Style {
+        Style::default().fg(self.accent).add_modifier(Modifier::UNDERLINED)
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_by_name() {
+        assert_eq!(Theme::by_name("dark"), Some(Theme::dark()));
+        assert_eq!(Theme::by_name(" Light "), Some(Theme::light()));
+        assert_eq!(Theme::by_name("solarized"), None);
+    }
+
+    #[test]
+    fn test_config_overrides() {
+        let mut config = VedaConfig {
+            theme: "light".to_string(),
+            ..VedaConfig::default()
+        };
+        config.colors.insert("user".to_string(), "red".to_string());
+        config.colors.insert("bogus_role".to_string(), "red".to_string());
+        config.colors.insert("claude".to_string(), "not-a-color".to_string());
+
+        let theme = Theme::from_config(&config);
+        assert_eq!(theme.name, "light");
+        assert_eq!(theme.user, Color::Red);
+        assert_eq!(theme.claude, Theme::light().claude);
+    }
+
+    #[test]
+    fn test_unknown_config_theme_falls_back_to_dark() {
+        let config = VedaConfig {
+            theme: "neon".to_string(),
+            ..VedaConfig::default()
+        };
+        assert_eq!(Theme::from_config(&config), Theme::dark());
+    }
+
+    #[test]
+    fn test_slice_colors_repeat_after_palette() {
+        let theme = Theme::dark();
+        let count = theme.slice_palette.len();
+        assert_ne!(theme.slice_color(0), theme.slice_color(1));
+        assert_eq!(theme.slice_color(1), theme.slice_color(count + 1));
+    }
+}
@@ -0,0 +1,193 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+
+/// Where a directory's last todo list is kept when `persist_todos` is on
+pub const TODO_FILE: &str = ".veda/todos.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TodoItem {
+    pub id: String,
+    pub content: String,
+    pub status: String,
+    pub priority: String,
+}
+
+impl TodoItem {
+    /// Build an item from a JSON object, requiring at least `content` and `status`
+    fn from_json(value: &Value) -> Option<Self> {
+        let obj = value.as_object()?;
+        let content = obj.get("content")?.as_str()?;
+        let status = obj.get("status")?.as_str()?;
+        Some(Self {
+            id: obj.get("id").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+            content: content.to_string(),
+            status: status.to_string(),
+            priority: obj.get("priority").and_then(|v| v.as_str()).unwrap_or("medium").to_string(),
+        })
+    }
+}
+
+/// Find the last JSON array in `text` that looks like a todo list.
+///
+/// Every balanced `[...]` region (ignoring brackets inside JSON strings) is tried
+/// in turn; a region is only accepted if it parses as a non-empty array whose
+/// entries all carry `content` and `status`. Code samples and other bracketed
+/// text are skipped instead of clobbering the todo list.
+pub fn extract_todo_items(text: &str) -> Option<Vec<TodoItem>> {
+    let mut found = None;
+    let mut search_from = 0;
+
+    while let Some(offset) = text[search_from..].find('[') {
+        let start = search_from + offset;
+        match balanced_region_end(text, start) {
+            Some(end) => match parse_todo_array(&text[start..=end]) {
+                Some(items) => {
+                    found = Some(items);
+                    search_from = end + 1;
+                }
+                // Not a todo list; nested arrays may still be one
+                None => search_from = start + 1,
+            },
+            None => search_from = start + 1,
+        }
+    }
+
+    found
+}
+
+/// Write `items` to `dir`'s todo file, creating `.veda/` if needed
+pub fn save_todos(dir: &Path, items: &[TodoItem]) -> Result<()> {
+    let path = dir.join(TODO_FILE);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(items)?)?;
+    Ok(())
+}
+
+/// The todo list last saved in `dir`, if there is a readable one
+pub fn load_todos(dir: &Path) -> Option<Vec<TodoItem>> {
+    let path = dir.join(TODO_FILE);
+    let contents = std::fs::read_to_string(&path).ok()?;
+    match serde_json::from_str(&contents) {
+        Ok(items) => Some(items),
+        Err(e) => {
+            tracing::warn!("Ignoring unreadable todo list {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Byte index of the `]` closing the `[` at `start`, skipping over JSON string contents
+fn balanced_region_end(text: &str, start: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (idx, c) in text[start..].char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(start + idx);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_todo_array(candidate: &str) -> Option<Vec<TodoItem>> {
+    let values: Vec<Value> = serde_json::from_str(candidate).ok()?;
+    if values.is_empty() {
+        return None;
+    }
+    values.iter().map(TodoItem::from_json).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TODOS: &str = r#"[{"id":"1","content":"Write parser","status":"in_progress","priority":"high"},{"id":"2","content":"Add tests","status":"pending"}]"#;
+
+    #[test]
+    fn test_saved_todos_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(load_todos(dir.path()), None);
+
+        let items = extract_todo_items(r#"[{"id":"1","content":"Write parser","status":"in_progress","priority":"high"}]"#).unwrap();
+        save_todos(dir.path(), &items).unwrap();
+        assert_eq!(load_todos(dir.path()), Some(items));
+
+        std::fs::write(dir.path().join(TODO_FILE), "not json").unwrap();
+        assert_eq!(load_todos(dir.path()), None);
+    }
+
+    #[test]
+    fn test_extracts_plain_todo_array() {
+        let items = extract_todo_items(TODOS).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].content, "Write parser");
+        assert_eq!(items[1].priority, "medium");
+    }
+
+    #[test]
+    fn test_ignores_code_sample_brackets_around_todos() {
+        let text = format!(
+            "Indexing looks like `let x = arr[i];` and vec![1, 2].\n\nHere are the todos: {}\n\nThen call items[0].",
+            TODOS
+        );
+        let items = extract_todo_items(&text).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[1].content, "Add tests");
+    }
+
+    #[test]
+    fn test_brackets_inside_strings_do_not_break_balance() {
+        let text = r#"[{"content":"Handle ] and [ in input","status":"pending"}]"#;
+        let items = extract_todo_items(text).unwrap();
+        assert_eq!(items[0].content, "Handle ] and [ in input");
+    }
+
+    #[test]
+    fn test_code_only_message_is_not_a_todo_list() {
+        let text = "fn main() {\n    let v = vec![1, 2, 3];\n    println!(\"{:?}\", &v[1..]);\n}\n\n```json\n[1, 2, 3]\n```";
+        assert_eq!(extract_todo_items(text), None);
+    }
+
+    #[test]
+    fn test_arrays_missing_required_fields_are_rejected() {
+        let text = r#"[{"name":"not a todo"}] and [] and [{"content":"no status"}]"#;
+        assert_eq!(extract_todo_items(text), None);
+    }
+
+    #[test]
+    fn test_last_valid_todo_array_wins() {
+        let text = r#"Before: [{"content":"old","status":"pending"}] After: [{"content":"new","status":"done"}]"#;
+        let items = extract_todo_items(text).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].content, "new");
+    }
+
+    #[test]
+    fn test_todo_array_nested_in_object() {
+        let text = r#"{"todos": [{"content":"nested","status":"pending"}]}"#;
+        let items = extract_todo_items(text).unwrap();
+        assert_eq!(items[0].content, "nested");
+    }
+}
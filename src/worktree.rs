@@ -0,0 +1,161 @@
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use std::path::Path;
+use std::process::Command;
+
+/// A `git worktree` Veda made for a spawned slice: its own checkout on its own branch
+#[derive(Debug, Clone, PartialEq)]
+pub struct Worktree {
+    /// Top level of the checkout the worktree was added to
+    pub repo: String,
+    /// Top level of the worktree itself
+    pub path: String,
+    pub branch: String,
+    /// Where the slice works: the worktree's counterpart of the directory it was made from
+    pub working_dir: String,
+}
+
+fn git(dir: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(anyhow!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()))
+    }
+}
+
+/// Add a worktree on a new `veda/<slice>-<time>` branch for the repository around
+/// `working_dir`. Worktrees go next to the repository, in `<repo>-veda-worktrees/`, so they
+/// never show up as untracked files in it. An untracked `.mcp.json` is copied across so
+/// Claude finds the same MCP servers.
+pub fn create(working_dir: &str, slice_name: &str) -> Result<Worktree> {
+    let repo = git(working_dir, &["rev-parse", "--show-toplevel"])?;
+    let prefix = git(working_dir, &["rev-parse", "--show-prefix"])?;
+    let repo_path = Path::new(&repo);
+    let repo_name = repo_path.file_name().map_or("repo".into(), |name| name.to_string_lossy());
+    let slug = format!(
+        "{}-{}",
+        slice_name.to_lowercase().split_whitespace().collect::<Vec<_>>().join("-"),
+        Local::now().format("%Y%m%d-%H%M%S")
+    );
+    let branch = format!("veda/{}", slug);
+    let path = repo_path
+        .parent()
+        .unwrap_or(repo_path)
+        .join(format!("{}-veda-worktrees", repo_name))
+        .join(&slug);
+    let path_str = path.to_string_lossy().to_string();
+    git(&repo, &["worktree", "add", "-b", &branch, &path_str])?;
+
+    let mcp_config = repo_path.join(".mcp.json");
+    if mcp_config.exists() && !path.join(".mcp.json").exists() {
+        if let Err(e) = std::fs::copy(&mcp_config, path.join(".mcp.json")) {
+            tracing::warn!("Couldn't copy .mcp.json into worktree {}: {}", path_str, e);
+        }
+    }
+
+    Ok(Worktree {
+        repo,
+        working_dir: path.join(&prefix).to_string_lossy().trim_end_matches('/').to_string(),
+        path: path_str,
+        branch,
+    })
+}
+
+impl Worktree {
+    /// Merge the worktree's branch into whatever the main checkout has checked out, then
+    /// remove the worktree and its branch. Uncommitted changes in the worktree would be lost,
+    /// so a dirty worktree is refused, and so is a dirty main checkout, which a failed merge
+    /// couldn't be cleanly backed out of. A conflicting merge is aborted and the worktree kept.
+    pub fn merge(&self) -> Result<()> {
+        // The .mcp.json copied in by `create` is no work of the slice's
+        if !git(&self.path, &["status", "--porcelain", "--", ".", ":!.mcp.json"])?.is_empty() {
+            return Err(anyhow!("{} has uncommitted changes; commit them in the worktree first", self.path));
+        }
+        // Untracked files are left alone by `merge --abort`, so only tracked changes count
+        if !git(&self.repo, &["status", "--porcelain", "--untracked-files=no"])?.is_empty() {
+            return Err(anyhow!("{} has uncommitted changes; commit or stash them before merging", self.repo));
+        }
+        if let Err(e) = git(&self.repo, &["merge", "--no-edit", &self.branch]) {
+            if let Err(abort) = git(&self.repo, &["merge", "--abort"]) {
+                tracing::warn!("Couldn't abort the merge of {} in {}: {}", self.branch, self.repo, abort);
+            }
+            return Err(e);
+        }
+        self.remove()
+    }
+
+    /// Delete the worktree and its branch, discarding anything not merged
+    pub fn remove(&self) -> Result<()> {
+        git(&self.repo, &["worktree", "remove", "--force", &self.path])?;
+        git(&self.repo, &["branch", "-D", &self.branch])?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init_repo(dir: &Path) -> String {
+        let repo = dir.join("project");
+        std::fs::create_dir_all(repo.join("src")).unwrap();
+        let repo = repo.to_string_lossy().to_string();
+        git(&repo, &["init", "-q"]).unwrap();
+        std::fs::write(Path::new(&repo).join("README.md"), "hello\n").unwrap();
+        std::fs::write(Path::new(&repo).join("src/lib.rs"), "\n").unwrap();
+        std::fs::write(Path::new(&repo).join(".mcp.json"), "{}").unwrap();
+        commit(&repo, "init");
+        repo
+    }
+
+    fn commit(dir: &str, message: &str) {
+        git(dir, &["add", "-A", "--", ".", ":!.mcp.json"]).unwrap();
+        git(dir, &["-c", "user.name=Veda", "-c", "user.email=veda@example.com", "commit", "-qm", message]).unwrap();
+    }
+
+    #[test]
+    fn test_worktree_merges_back_and_cleans_up() {
+        let temp = tempfile::tempdir().unwrap();
+        let repo = init_repo(temp.path());
+
+        let worktree = create(&format!("{}/src", repo), "Slice 1").unwrap();
+        assert!(worktree.branch.starts_with("veda/slice-1-"));
+        assert_eq!(worktree.working_dir, format!("{}/src", worktree.path));
+        assert!(Path::new(&worktree.path).join(".mcp.json").exists());
+        assert!(!worktree.path.starts_with(&format!("{}/", repo)));
+
+        std::fs::write(Path::new(&worktree.path).join("src/new.rs"), "fn main() {}\n").unwrap();
+        assert!(worktree.merge().unwrap_err().to_string().contains("uncommitted changes"));
+        commit(&worktree.working_dir, "add new.rs");
+        worktree.merge().unwrap();
+
+        assert!(Path::new(&repo).join("src/new.rs").exists());
+        assert!(!Path::new(&worktree.path).exists());
+        assert!(git(&repo, &["branch", "--list", &worktree.branch]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_conflicting_merge_is_aborted_and_keeps_the_worktree() {
+        let temp = tempfile::tempdir().unwrap();
+        let repo = init_repo(temp.path());
+        let worktree = create(&repo, "Slice 1").unwrap();
+        std::fs::write(Path::new(&worktree.path).join("README.md"), "from the slice\n").unwrap();
+        commit(&worktree.path, "slice edit");
+
+        std::fs::write(Path::new(&repo).join("README.md"), "uncommitted\n").unwrap();
+        assert!(worktree.merge().unwrap_err().to_string().contains("commit or stash"));
+        commit(&repo, "main edit");
+
+        assert!(worktree.merge().is_err());
+        assert!(git(&repo, &["status", "--porcelain", "--untracked-files=no"]).unwrap().is_empty());
+        assert_eq!(std::fs::read_to_string(Path::new(&repo).join("README.md")).unwrap(), "uncommitted\n");
+        assert!(Path::new(&worktree.path).exists());
+    }
+
+    #[test]
+    fn test_outside_a_repository_fails() {
+        let temp = tempfile::tempdir().unwrap();
+        assert!(create(&temp.path().to_string_lossy(), "Slice 1").is_err());
+    }
+}